@@ -0,0 +1,102 @@
+// Settings read from $XDG_CONFIG_HOME/oxmark/config.toml, all of them optional
+
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+const CONFIG_DIR_NAME: &str = "oxmark";
+const CONFIG_FILE_NAME: &str = "config.toml";
+
+// Environment variable overriding the backend of the configuration file
+const BACKEND_ENV: &str = "OXMARK_BACKEND";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub backend: Backend,
+}
+
+// How the bookmarks are stored
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    // A SQLite database, with full-text search and migrations
+    #[default]
+    Sqlite,
+    // A TOML file that can be edited by hand
+    Toml,
+}
+
+impl Backend {
+    pub fn parse(backend: &str) -> Option<Backend> {
+        match backend {
+            "sqlite" => Some(Backend::Sqlite),
+            "toml" => Some(Backend::Toml),
+            _ => None,
+        }
+    }
+
+    // Of the files profiles are stored in
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Backend::Sqlite => "db",
+            Backend::Toml => "toml",
+        }
+    }
+}
+
+// The configuration file, with the backend overridden by OXMARK_BACKEND.
+// A missing file is the default configuration
+pub fn load() -> Result<Config, ConfigError> {
+    let mut config = match config_path().filter(|path| path.exists()) {
+        Some(path) => {
+            let content = std::fs::read_to_string(&path).map_err(ConfigError::IoError)?;
+            toml::from_str(&content).map_err(|e| ConfigError::ParseError(path, e))?
+        }
+        None => Config::default(),
+    };
+
+    if let Some(backend) = crate::paths::non_empty_env(BACKEND_ENV) {
+        config.backend = Backend::parse(&backend).ok_or(ConfigError::InvalidBackend(backend))?;
+    }
+    Ok(config)
+}
+
+fn config_path() -> Option<PathBuf> {
+    crate::paths::xdg_config_home()
+        .map(|config_home| config_home.join(CONFIG_DIR_NAME).join(CONFIG_FILE_NAME))
+}
+
+//
+// ConfigError
+//
+
+#[derive(Debug)]
+pub enum ConfigError {
+    IoError(std::io::Error),
+    ParseError(PathBuf, toml::de::Error),
+    InvalidBackend(String),
+}
+
+impl ConfigError {
+    pub fn message(&self) -> String {
+        match self {
+            ConfigError::IoError(e) => format!("Could not read the configuration: {}", e),
+            ConfigError::ParseError(path, e) => {
+                format!("Invalid configuration in {}: {}", path.display(), e)
+            }
+            ConfigError::InvalidBackend(backend) => format!(
+                "Invalid {} '{}': expected sqlite or toml",
+                BACKEND_ENV, backend
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "oxmark - ConfigError: {}", self.message())
+    }
+}
+
+impl std::error::Error for ConfigError {}
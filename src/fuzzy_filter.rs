@@ -0,0 +1,136 @@
+use crate::Bookmark;
+use nucleo_matcher::pattern::CaseMatching;
+use nucleo_matcher::pattern::Normalization;
+use nucleo_matcher::{pattern::Pattern, Config, Matcher, Utf32Str};
+
+// Search terms starting with this prefix filter by tag instead of fuzzy matching.
+// Tags are matched by prefix so the list narrows down while the tag is being typed
+const TAG_PREFIX: &str = "tag:";
+
+pub fn get_filtered_bookmarks(bookmarks: Vec<Bookmark>, search_term: String) -> Vec<Bookmark> {
+    get_scored_bookmarks(bookmarks, search_term)
+        .into_iter()
+        .map(|(bookmark, _)| bookmark)
+        .collect()
+}
+
+// The bookmarks matching the search term, best match first.
+// Equal scores keep their original order
+pub fn get_ranked_bookmarks(bookmarks: Vec<Bookmark>, search_term: String) -> Vec<Bookmark> {
+    let mut scored_bookmarks = get_scored_bookmarks(bookmarks, search_term);
+    scored_bookmarks.sort_by(|(_, a), (_, b)| b.cmp(a));
+    scored_bookmarks
+        .into_iter()
+        .map(|(bookmark, _)| bookmark)
+        .collect()
+}
+
+// The bookmarks matching the search term with their fuzzy score, in their original order
+pub fn get_scored_bookmarks(bookmarks: Vec<Bookmark>, search_term: String) -> Vec<(Bookmark, u32)> {
+    let (tags, search_term) = split_search_term(&search_term);
+
+    let mut matcher = Matcher::new(Config::DEFAULT);
+    let pattern = Pattern::parse(&search_term, CaseMatching::Ignore, Normalization::Smart);
+    let mut buffer = Vec::new();
+
+    let mut scored_bookmarks: Vec<(Bookmark, u32)> = Vec::new();
+
+    for bookmark in bookmarks {
+        if !has_tags(&bookmark, &tags) {
+            continue;
+        }
+
+        let haystack = get_haystack(&bookmark);
+        let score = pattern.score(Utf32Str::new(&haystack, &mut buffer), &mut matcher);
+        if let Some(score) = score {
+            scored_bookmarks.push((bookmark, score));
+        }
+    }
+
+    scored_bookmarks
+}
+
+// Merge fuzzy matches on the name with full-text search hits, best combined score first.
+// Both scores are normalized to [0, 1] so that neither kind of match dominates
+pub fn get_blended_bookmarks(
+    bookmarks: Vec<Bookmark>,
+    fts_results: Vec<(Bookmark, f64)>,
+    search_term: String,
+) -> Vec<Bookmark> {
+    let (tags, fts_term) = split_search_term(&search_term);
+
+    // Without a query there is nothing to rank, keep the current order
+    if fts_term.is_empty() {
+        return get_filtered_bookmarks(bookmarks, search_term);
+    }
+
+    let fuzzy_results = get_scored_bookmarks(bookmarks, search_term);
+    let max_fuzzy_score = fuzzy_results
+        .iter()
+        .map(|(_, score)| *score)
+        .max()
+        .unwrap_or(0)
+        .max(1) as f64;
+
+    // bm25 ranks are negative, lower being better
+    let max_fts_score = fts_results
+        .iter()
+        .map(|(_, rank)| -rank)
+        .fold(f64::EPSILON, f64::max);
+
+    let mut blended: Vec<(Bookmark, f64)> = fuzzy_results
+        .into_iter()
+        .map(|(bookmark, score)| (bookmark, score as f64 / max_fuzzy_score))
+        .collect();
+
+    for (bookmark, rank) in fts_results {
+        if !has_tags(&bookmark, &tags) {
+            continue;
+        }
+
+        let fts_score = -rank / max_fts_score;
+        match blended.iter_mut().find(|(b, _)| b.id == bookmark.id) {
+            Some((_, score)) => *score += fts_score,
+            None => blended.push((bookmark, fts_score)),
+        }
+    }
+
+    blended.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+    blended.into_iter().map(|(bookmark, _)| bookmark).collect()
+}
+
+// The name is matched along with the folder path, so that "backend api"
+// finds the bookmark named api in the work/backend folder
+fn get_haystack(bookmark: &Bookmark) -> String {
+    let name = bookmark.name.clone().unwrap_or("".to_string());
+    match &bookmark.folder {
+        Some(folder) => format!("{}/{}", folder, name),
+        None => name,
+    }
+}
+
+fn has_tags(bookmark: &Bookmark, tags: &[String]) -> bool {
+    tags.iter().all(|tag| {
+        bookmark
+            .tags
+            .iter()
+            .any(|bookmark_tag| bookmark_tag.to_lowercase().starts_with(tag))
+    })
+}
+
+// Split the search term into the `tag:` filters and the remaining fuzzy query
+pub fn split_search_term(search_term: &str) -> (Vec<String>, String) {
+    let mut tags = Vec::new();
+    let mut words = Vec::new();
+
+    for word in search_term.split_whitespace() {
+        match word.strip_prefix(TAG_PREFIX) {
+            Some(tag) if !tag.is_empty() => tags.push(tag.to_lowercase()),
+            // A bare "tag:" is still being typed, so it filters nothing yet
+            Some(_) => {}
+            None => words.push(word),
+        }
+    }
+
+    (tags, words.join(" "))
+}
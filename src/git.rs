@@ -0,0 +1,89 @@
+// Git state of bookmarked repositories, shown in the preview
+
+use std::path::Path;
+use std::process::Command;
+
+// How many commit subjects the preview shows
+const RECENT_COMMITS: usize = 5;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GitStatus {
+    // The checked out branch, None when the head is detached
+    pub branch: Option<String>,
+    // Abbreviated commit the head points to, None before the first commit
+    pub commit: Option<String>,
+    // Commits ahead of and behind the upstream, None without an upstream
+    pub ahead_behind: Option<(u32, u32)>,
+    // Tracked files with staged or unstaged changes, including conflicts
+    pub modified: usize,
+    pub untracked: usize,
+    // Subjects of the last commits, newest first
+    pub recent_commits: Vec<String>,
+}
+
+impl GitStatus {
+    pub fn is_dirty(&self) -> bool {
+        self.modified > 0 || self.untracked > 0
+    }
+}
+
+// The status of the work tree holding the directory, None if it is not in one.
+// Runs git, so it is only called off the UI thread
+pub fn get_status(directory: &Path) -> Option<GitStatus> {
+    if !directory.is_dir() {
+        return None;
+    }
+
+    let output = git(directory, &["status", "--porcelain=v2", "--branch"])?;
+    let mut status = parse_status(&output);
+    status.recent_commits = git(
+        directory,
+        &["log", &format!("-{}", RECENT_COMMITS), "--format=%s"],
+    )
+    .map(|output| output.lines().map(|line| line.to_string()).collect())
+    .unwrap_or_default();
+
+    Some(status)
+}
+
+// The output of a successful git command, None when it fails or git is missing
+fn git(directory: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(directory)
+        .args(args)
+        // Keep `git status` from refreshing the index, which would race with the user's git
+        .env("GIT_OPTIONAL_LOCKS", "0")
+        .stdin(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .output()
+        .ok()?;
+
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+// Read the header and entry lines of `git status --porcelain=v2 --branch`
+fn parse_status(output: &str) -> GitStatus {
+    let mut status = GitStatus::default();
+    for line in output.lines() {
+        if let Some(oid) = line.strip_prefix("# branch.oid ") {
+            status.commit = (oid != "(initial)").then(|| oid.chars().take(7).collect());
+        } else if let Some(head) = line.strip_prefix("# branch.head ") {
+            status.branch = (head != "(detached)").then(|| head.to_string());
+        } else if let Some(counts) = line.strip_prefix("# branch.ab ") {
+            // "+ahead -behind"
+            let mut counts = counts
+                .split_whitespace()
+                .map(|count| count[1..].parse::<u32>().unwrap_or(0));
+            status.ahead_behind = Some((counts.next().unwrap_or(0), counts.next().unwrap_or(0)));
+        } else if line.starts_with("1 ") || line.starts_with("2 ") || line.starts_with("u ") {
+            status.modified += 1;
+        } else if line.starts_with("? ") {
+            status.untracked += 1;
+        }
+    }
+    status
+}
@@ -0,0 +1,184 @@
+// Parsers for the databases of frequency based directory jumpers
+
+use super::{ImportEntry, ImportError};
+
+// zoxide's db.zo is a version number followed by a bincode encoded list of directories
+const ZOXIDE_VERSION: u32 = 3;
+
+pub fn parse_zoxide(contents: &[u8]) -> Result<Vec<ImportEntry>, ImportError> {
+    let mut reader = BinaryReader {
+        contents,
+        offset: 0,
+    };
+
+    let version = reader.read_u32()?;
+    if version != ZOXIDE_VERSION {
+        return Err(ImportError::ParseError(format!(
+            "unsupported zoxide database version {}",
+            version
+        )));
+    }
+
+    let count = reader.read_u64()?;
+    let mut entries = Vec::new();
+    for _ in 0..count {
+        let path_length = reader.read_u64()? as usize;
+        let path = String::from_utf8_lossy(reader.read_bytes(path_length)?).to_string();
+        let rank = f64::from_le_bytes(reader.read_bytes(8)?.try_into().unwrap());
+        let last_accessed = reader.read_u64()?;
+
+        let mut entry = ImportEntry::new(path, None);
+        entry.score = Some(rank);
+        entry.bookmark.last_visited = Some(last_accessed as i64);
+        entries.push(entry);
+    }
+
+    Ok(entries)
+}
+
+// autojump keeps one "weight<TAB>path" line per directory
+pub fn parse_autojump(contents: &str) -> Result<Vec<ImportEntry>, ImportError> {
+    let mut entries = Vec::new();
+
+    for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+        let Some((weight, path)) = line.split_once('\t') else {
+            return Err(ImportError::ParseError(format!("invalid line '{}'", line)));
+        };
+        let weight = weight
+            .trim()
+            .parse()
+            .map_err(|_| ImportError::ParseError(format!("invalid weight in line '{}'", line)))?;
+
+        let mut entry = ImportEntry::new(path.to_string(), None);
+        entry.score = Some(weight);
+        entries.push(entry);
+    }
+
+    Ok(entries)
+}
+
+// z and fasd keep one "path|rank|time" line per directory
+pub fn parse_z(contents: &str) -> Result<Vec<ImportEntry>, ImportError> {
+    let mut entries = Vec::new();
+
+    for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+        // The path itself may contain '|', so split from the end
+        let mut fields = line.rsplitn(3, '|');
+        let (Some(time), Some(rank), Some(path)) = (fields.next(), fields.next(), fields.next())
+        else {
+            return Err(ImportError::ParseError(format!("invalid line '{}'", line)));
+        };
+
+        let rank = rank
+            .parse()
+            .map_err(|_| ImportError::ParseError(format!("invalid rank in line '{}'", line)))?;
+        let time = time
+            .parse()
+            .map_err(|_| ImportError::ParseError(format!("invalid time in line '{}'", line)))?;
+
+        let mut entry = ImportEntry::new(path.to_string(), None);
+        entry.score = Some(rank);
+        entry.bookmark.last_visited = Some(time);
+        entries.push(entry);
+    }
+
+    Ok(entries)
+}
+
+struct BinaryReader<'a> {
+    contents: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> BinaryReader<'a> {
+    fn read_bytes(&mut self, length: usize) -> Result<&'a [u8], ImportError> {
+        let bytes = self
+            .contents
+            .get(self.offset..self.offset.saturating_add(length))
+            .ok_or(ImportError::ParseError(
+                "unexpected end of the zoxide database".to_string(),
+            ))?;
+        self.offset += length;
+        Ok(bytes)
+    }
+
+    fn read_u32(&mut self) -> Result<u32, ImportError> {
+        Ok(u32::from_le_bytes(self.read_bytes(4)?.try_into().unwrap()))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, ImportError> {
+        Ok(u64::from_le_bytes(self.read_bytes(8)?.try_into().unwrap()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The path, score and last visit of an entry
+    type Summary = (String, Option<f64>, Option<i64>);
+    type Case<'a> = (Parser, &'a str, Option<(&'a str, f64, Option<i64>)>);
+    type Parser = fn(&str) -> Result<Vec<ImportEntry>, ImportError>;
+
+    fn summarize(result: Result<Vec<ImportEntry>, ImportError>) -> Result<Vec<Summary>, ()> {
+        let entries = result.map_err(|_| ())?;
+        Ok(entries
+            .into_iter()
+            .map(|entry| (entry.path(), entry.score, entry.bookmark.last_visited))
+            .collect())
+    }
+
+    fn zoxide_database(version: u32, paths: &[(&str, f64, u64)]) -> Vec<u8> {
+        let mut contents = version.to_le_bytes().to_vec();
+        contents.extend((paths.len() as u64).to_le_bytes());
+        for (path, rank, last_accessed) in paths {
+            contents.extend((path.len() as u64).to_le_bytes());
+            contents.extend(path.as_bytes());
+            contents.extend(rank.to_le_bytes());
+            contents.extend(last_accessed.to_le_bytes());
+        }
+        contents
+    }
+
+    #[test]
+    fn parse_text_databases() {
+        let cases: [Case; 5] = [
+            (
+                parse_autojump,
+                "23.4\t/home/me/projects",
+                Some(("/home/me/projects", 23.4, None)),
+            ),
+            (parse_autojump, "/home/me/projects 23.4", None),
+            (
+                parse_z,
+                "/srv/a|b|12|1700000000",
+                Some(("/srv/a|b", 12.0, Some(1700000000))),
+            ),
+            (parse_z, "/srv/app|high|1700000000", None),
+            (parse_z, "/srv/app", None),
+        ];
+
+        for (parse, line, expected) in cases {
+            let expected =
+                expected.map(|(path, score, time)| vec![(path.to_string(), Some(score), time)]);
+            assert_eq!(summarize(parse(line)).ok(), expected, "{}", line);
+        }
+    }
+
+    #[test]
+    fn parse_zoxide_database() {
+        let contents = zoxide_database(3, &[("/home/me/projects", 4.5, 1700000000)]);
+        assert_eq!(
+            summarize(parse_zoxide(&contents)),
+            Ok(vec![(
+                "/home/me/projects".to_string(),
+                Some(4.5),
+                Some(1700000000)
+            )])
+        );
+
+        let truncated = &contents[..contents.len() - 4];
+        assert!(parse_zoxide(truncated).is_err());
+        assert!(parse_zoxide(&zoxide_database(2, &[])).is_err());
+    }
+}
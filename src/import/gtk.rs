@@ -0,0 +1,181 @@
+// The sidebar bookmarks shared by GTK file managers such as Nautilus.
+// Each line is a URI, optionally followed by a space and a label
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use super::ImportEntry;
+use crate::paths;
+use crate::Bookmark;
+
+const FILE_URI_PREFIX: &str = "file://";
+
+// Characters left as is in file URIs, like GLib's g_filename_to_uri does
+const URI_SAFE_CHARACTERS: &str = "-._~/!$&'()*+,;=:@";
+
+pub fn bookmarks_file() -> Option<PathBuf> {
+    paths::xdg_config_home().map(|config_home| config_home.join("gtk-3.0").join("bookmarks"))
+}
+
+// Only local files are imported, network locations cannot be bookmarked
+pub fn parse_gtk(contents: &str) -> Vec<ImportEntry> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let (uri, label) = split_line(line);
+            let path = path_from_uri(uri)?;
+            Some(ImportEntry::new(path, label.map(|label| label.to_string())))
+        })
+        .collect()
+}
+
+// Add the given bookmarks to the bookmarks file. Entries for the same paths are replaced
+// where they are, and every other line is kept as it was. Only the first bookmark of a path is
+// written. Bookmarks named after their directory get no label, as GTK shows that name already
+pub fn write_gtk(contents: &str, bookmarks: &[Bookmark]) -> String {
+    let mut written_paths = HashSet::new();
+    let mut new_lines: Vec<(String, Option<String>)> = bookmarks
+        .iter()
+        .filter_map(|bookmark| {
+            let path = bookmark
+                .path
+                .as_ref()
+                .filter(|path| written_paths.insert(*path))?;
+            let uri = uri_from_path(path);
+
+            let basename = std::path::Path::new(path)
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string());
+            let line = match &bookmark.name {
+                Some(name) if Some(name) != basename.as_ref() => format!("{} {}", uri, name),
+                _ => uri,
+            };
+            Some((path.clone(), Some(line)))
+        })
+        .collect();
+
+    let mut lines: Vec<String> = contents
+        .lines()
+        .map(|line| {
+            let path = path_from_uri(split_line(line).0);
+            let replacement = new_lines
+                .iter_mut()
+                .find(|(new_path, _)| Some(new_path) == path.as_ref())
+                .and_then(|(_, new_line)| new_line.take());
+            replacement.unwrap_or(line.to_string())
+        })
+        .collect();
+    lines.extend(new_lines.into_iter().filter_map(|(_, new_line)| new_line));
+
+    lines.iter().map(|line| format!("{}\n", line)).collect()
+}
+
+fn split_line(line: &str) -> (&str, Option<&str>) {
+    match line.trim().split_once(' ') {
+        Some((uri, label)) if !label.trim().is_empty() => (uri, Some(label.trim())),
+        Some((uri, _)) => (uri, None),
+        None => (line.trim(), None),
+    }
+}
+
+fn path_from_uri(uri: &str) -> Option<String> {
+    let path = uri.strip_prefix(FILE_URI_PREFIX)?;
+    // file://host/path URIs point to other machines
+    if !path.starts_with('/') {
+        return None;
+    }
+    Some(percent_decode(path))
+}
+
+fn uri_from_path(path: &str) -> String {
+    let mut uri = FILE_URI_PREFIX.to_string();
+    for byte in path.bytes() {
+        if byte.is_ascii_alphanumeric() || URI_SAFE_CHARACTERS.contains(byte as char) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    uri
+}
+
+// Invalid escapes are kept as they are
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = bytes
+            .get(index + 1..index + 3)
+            .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[index], escaped) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                index += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bookmark(path: &str, name: &str) -> Bookmark {
+        Bookmark {
+            name: Some(name.to_string()),
+            path: Some(path.to_string()),
+            ..Bookmark::default()
+        }
+    }
+
+    #[test]
+    fn parse_gtk_lines() {
+        let cases = [
+            (
+                "file:///home/me/My%20Projects",
+                Some(("/home/me/My Projects", None)),
+            ),
+            (
+                "file:///srv/app App server",
+                Some(("/srv/app", Some("App server"))),
+            ),
+            ("sftp://host/srv/app remote", None),
+            ("file://host/srv/app", None),
+        ];
+        for (line, expected) in cases {
+            let entries = parse_gtk(line);
+            let entry = entries.first().map(|entry| {
+                let bookmark = &entry.bookmark;
+                (bookmark.path.clone().unwrap(), bookmark.name.clone())
+            });
+            let expected =
+                expected.map(|(path, name)| (path.to_string(), name.map(|name| name.to_string())));
+            assert_eq!(entry, expected, "{}", line);
+        }
+    }
+
+    #[test]
+    fn write_gtk_replaces_in_place_and_writes_each_path_once() {
+        let contents = "file:///srv/other Other\nfile:///srv/app Old label\nsftp://host/x\n";
+        let bookmarks = [
+            bookmark("/srv/app", "api"),
+            bookmark("/srv/app", "duplicate"),
+            bookmark("/srv/new", "new"),
+        ];
+
+        assert_eq!(
+            write_gtk(contents, &bookmarks),
+            "file:///srv/other Other\nfile:///srv/app api\nsftp://host/x\nfile:///srv/new\n"
+        );
+    }
+}
@@ -0,0 +1,142 @@
+// Parsers for the files of named directory bookmark tools
+
+use super::ImportEntry;
+use crate::paths;
+
+// bashmarks keeps one `export DIR_<name>="<path>"` line per mark
+pub fn parse_bashmarks(contents: &str) -> Vec<ImportEntry> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let (name, path) = line.trim().strip_prefix("export DIR_")?.split_once('=')?;
+            Some(get_entry(name, unquote(path)))
+        })
+        .collect()
+}
+
+// wd keeps one "name:path" line per warp point
+pub fn parse_wd(contents: &str) -> Vec<ImportEntry> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let (name, path) = line.trim().split_once(':')?;
+            Some(get_entry(name, path))
+        })
+        .collect()
+}
+
+// fzf-marks keeps one "name : path" line per mark
+pub fn parse_fzf_marks(contents: &str) -> Vec<ImportEntry> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let (name, path) = line.split_once(" : ")?;
+            Some(get_entry(name, path))
+        })
+        .collect()
+}
+
+// apparix keeps one "j,path,name" line per bookmark, other line kinds are not bookmarks.
+// The name is split off the end, as the path may itself contain commas
+pub fn parse_apparix(contents: &str) -> Vec<ImportEntry> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let (path, name) = line.trim().strip_prefix("j,")?.rsplit_once(',')?;
+            Some(get_entry(name, path))
+        })
+        .collect()
+}
+
+// CDPATH is a colon separated list of directories, named after their last component
+pub fn parse_cdpath(cdpath: &str) -> Vec<ImportEntry> {
+    cdpath
+        .split(':')
+        // An empty entry stands for the current directory
+        .filter(|path| !path.is_empty() && *path != ".")
+        .map(|path| ImportEntry::new(expand_home(path), None))
+        .collect()
+}
+
+fn get_entry(name: &str, path: &str) -> ImportEntry {
+    let name = name.trim();
+    ImportEntry::new(
+        expand_home(path.trim()),
+        (!name.is_empty()).then(|| name.to_string()),
+    )
+}
+
+fn unquote(value: &str) -> &str {
+    let value = value.trim();
+    value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .or(value
+            .strip_prefix('\'')
+            .and_then(|value| value.strip_suffix('\'')))
+        .unwrap_or(value)
+}
+
+// Replace a leading ~, $HOME or ${HOME} with the home directory
+fn expand_home(path: &str) -> String {
+    let Some(home) = paths::home_dir() else {
+        return path.to_string();
+    };
+    let home = home.to_string_lossy();
+
+    for prefix in ["~", "$HOME", "${HOME}"] {
+        if let Some(rest) = path.strip_prefix(prefix) {
+            if rest.is_empty() || rest.starts_with('/') {
+                return format!("{}{}", home, rest);
+            }
+        }
+    }
+    path.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Parser = fn(&str) -> Vec<ImportEntry>;
+    type Case<'a> = (Parser, &'a str, Option<(&'a str, &'a str)>);
+
+    #[test]
+    fn parse_marks() {
+        let cases: [Case; 8] = [
+            (
+                parse_bashmarks,
+                "export DIR_api=\"/srv/api\"",
+                Some(("api", "/srv/api")),
+            ),
+            (parse_bashmarks, "alias api=/srv/api", None),
+            (parse_wd, "api:/srv/api", Some(("api", "/srv/api"))),
+            (parse_wd, "/srv/api", None),
+            (parse_fzf_marks, "api : /srv/api", Some(("api", "/srv/api"))),
+            (parse_fzf_marks, "api:/srv/api", None),
+            (parse_apparix, "j,/srv/a,b,api", Some(("api", "/srv/a,b"))),
+            (parse_apparix, "e,/srv/api,api", None),
+        ];
+
+        for (parse, line, expected) in cases {
+            let entries: Vec<(String, String)> = parse(line)
+                .into_iter()
+                .map(|entry| (entry.bookmark.name.clone().unwrap(), entry.path()))
+                .collect();
+            let expected: Vec<(String, String)> = expected
+                .into_iter()
+                .map(|(name, path)| (name.to_string(), path.to_string()))
+                .collect();
+            assert_eq!(entries, expected, "{}", line);
+        }
+    }
+
+    #[test]
+    fn parse_cdpath_skips_the_current_directory() {
+        let paths: Vec<String> = parse_cdpath("/srv:.::/home/me/src")
+            .iter()
+            .map(ImportEntry::path)
+            .collect();
+        assert_eq!(paths, ["/srv", "/home/me/src"]);
+    }
+}
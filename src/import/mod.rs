@@ -0,0 +1,261 @@
+use std::path::PathBuf;
+
+use clap::ValueEnum;
+
+use crate::paths;
+use crate::Bookmark;
+
+mod frecency;
+mod gtk;
+mod marks;
+mod portable;
+
+pub use gtk::{bookmarks_file as gtk_bookmarks_file, write_gtk};
+pub use portable::{export, ExportFormat};
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum ImportSource {
+    Zoxide,
+    Autojump,
+    Fasd,
+    Z,
+    Bashmarks,
+    Wd,
+    FzfMarks,
+    Apparix,
+    // The directories listed in the CDPATH environment variable
+    Cdpath,
+    // The sidebar bookmarks of GTK file managers
+    Gtk,
+    // Files written by `oxmark export`
+    Json,
+    Toml,
+    Csv,
+}
+
+// What to do with an imported bookmark whose name is already used
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum NameConflict {
+    // Do not import it
+    Skip,
+    // Import it with a numbered suffix added to its name
+    Rename,
+    // Point the existing bookmark to the imported path
+    Overwrite,
+}
+
+// Whether imported bookmarks are added to the existing ones or replace them
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum ImportMode {
+    Merge,
+    // Delete every existing bookmark first
+    Replace,
+}
+
+// A bookmark candidate read from another tool's data or from an export.
+// Other tools only fill in the path, and sometimes the name and last visit
+#[derive(Debug, Clone, Default)]
+pub struct ImportEntry {
+    pub bookmark: Bookmark,
+
+    // Ranking of the path in the source tool, if it keeps one
+    pub score: Option<f64>,
+}
+
+impl ImportEntry {
+    pub fn new(path: String, name: Option<String>) -> ImportEntry {
+        ImportEntry {
+            bookmark: Bookmark {
+                name,
+                path: Some(path),
+                ..Default::default()
+            },
+            score: None,
+        }
+    }
+
+    pub fn path(&self) -> String {
+        self.bookmark.path.clone().unwrap_or_default()
+    }
+}
+
+impl ImportSource {
+    // Where the source tool keeps its data by default
+    pub fn default_file(&self) -> Option<PathBuf> {
+        match self {
+            ImportSource::Zoxide => paths::non_empty_env("_ZO_DATA_DIR")
+                .map(PathBuf::from)
+                .or(paths::xdg_data_home().map(|data_home| data_home.join("zoxide")))
+                .map(|data_dir| data_dir.join("db.zo")),
+            ImportSource::Autojump => paths::xdg_data_home()
+                .map(|data_home| data_home.join("autojump").join("autojump.txt")),
+            ImportSource::Fasd => paths::non_empty_env("_FASD_DATA")
+                .map(PathBuf::from)
+                .or(paths::home_dir().map(|home| home.join(".fasd"))),
+            ImportSource::Z => paths::non_empty_env("_Z_DATA")
+                .map(PathBuf::from)
+                .or(paths::home_dir().map(|home| home.join(".z"))),
+            ImportSource::Bashmarks => paths::non_empty_env("SDIRS")
+                .map(PathBuf::from)
+                .or(paths::home_dir().map(|home| home.join(".sdirs"))),
+            ImportSource::Wd => paths::home_dir().map(|home| home.join(".warprc")),
+            ImportSource::FzfMarks => paths::non_empty_env("FZF_MARKS_FILE")
+                .map(PathBuf::from)
+                .or(paths::home_dir().map(|home| home.join(".fzf-marks"))),
+            ImportSource::Apparix => paths::home_dir().map(|home| home.join(".apparixrc")),
+            ImportSource::Gtk => gtk::bookmarks_file(),
+            ImportSource::Cdpath | ImportSource::Json | ImportSource::Toml | ImportSource::Csv => {
+                None
+            }
+        }
+    }
+
+    pub fn read_entries(&self, file: Option<PathBuf>) -> Result<Vec<ImportEntry>, ImportError> {
+        // CDPATH is read from the environment rather than from a file
+        if *self == ImportSource::Cdpath {
+            let cdpath = paths::non_empty_env("CDPATH").ok_or(ImportError::CdpathNotSet)?;
+            return Ok(marks::parse_cdpath(&cdpath));
+        }
+
+        let file = file
+            .or(self.default_file())
+            .ok_or(ImportError::NoDefaultFile)?;
+
+        let contents = std::fs::read(&file).map_err(|e| ImportError::IoError(file.clone(), e))?;
+
+        match self {
+            ImportSource::Zoxide => frecency::parse_zoxide(&contents),
+            ImportSource::Autojump => frecency::parse_autojump(&String::from_utf8_lossy(&contents)),
+            // fasd uses the same format as z
+            ImportSource::Fasd | ImportSource::Z => {
+                frecency::parse_z(&String::from_utf8_lossy(&contents))
+            }
+            ImportSource::Bashmarks => {
+                Ok(marks::parse_bashmarks(&String::from_utf8_lossy(&contents)))
+            }
+            ImportSource::Wd => Ok(marks::parse_wd(&String::from_utf8_lossy(&contents))),
+            ImportSource::FzfMarks => {
+                Ok(marks::parse_fzf_marks(&String::from_utf8_lossy(&contents)))
+            }
+            ImportSource::Apparix => Ok(marks::parse_apparix(&String::from_utf8_lossy(&contents))),
+            ImportSource::Gtk => Ok(gtk::parse_gtk(&String::from_utf8_lossy(&contents))),
+            ImportSource::Json => portable::parse(&contents, ExportFormat::Json),
+            ImportSource::Toml => portable::parse(&contents, ExportFormat::Toml),
+            ImportSource::Csv => portable::parse(&contents, ExportFormat::Csv),
+            ImportSource::Cdpath => unreachable!(),
+        }
+    }
+
+    // Exports carry whole bookmarks, so their paths are kept even when missing
+    pub fn is_portable(&self) -> bool {
+        matches!(
+            self,
+            ImportSource::Json | ImportSource::Toml | ImportSource::Csv
+        )
+    }
+}
+
+// Options shared by all the import sources
+#[derive(Debug, Clone, Copy)]
+pub struct ImportOptions {
+    // Skip entries ranked lower than this
+    pub min_score: Option<f64>,
+    pub on_conflict: NameConflict,
+    pub mode: ImportMode,
+    // Import the bookmarks even if their path does not exist on this machine
+    pub keep_missing: bool,
+    // Report what would happen without changing the database
+    pub dry_run: bool,
+    // Keep the hooks and variables of the imported bookmarks, which are shell code run on jump
+    pub with_hooks: bool,
+}
+
+// What happened to each entry of an import
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    pub replaced: Vec<String>,
+    pub imported: Vec<String>,
+    pub imported_missing: Vec<String>,
+    pub renamed: Vec<String>,
+    pub overwritten: Vec<String>,
+    pub already_bookmarked: Vec<String>,
+    pub name_taken: Vec<String>,
+    pub below_threshold: Vec<String>,
+    pub missing: Vec<String>,
+    pub hooks_left_out: Vec<String>,
+    // Every imported hook and variable, always shown in full
+    pub hooks: Vec<String>,
+}
+
+impl ImportSummary {
+    pub fn print(&self, verbose: bool) {
+        let sections = [
+            ("Moved to the trash, replaced by the import", &self.replaced),
+            ("Imported", &self.imported),
+            ("Imported, missing on disk", &self.imported_missing),
+            ("Imported under a new name", &self.renamed),
+            ("Overwritten", &self.overwritten),
+            ("Skipped, path already bookmarked", &self.already_bookmarked),
+            ("Skipped, name already used", &self.name_taken),
+            ("Skipped, below the score threshold", &self.below_threshold),
+            ("Skipped, missing on disk", &self.missing),
+            (
+                "Hooks left out, import them with --with-hooks",
+                &self.hooks_left_out,
+            ),
+        ];
+
+        if sections.iter().all(|(_, paths)| paths.is_empty()) {
+            println!("Nothing to import");
+            return;
+        }
+
+        for (title, paths) in sections {
+            if paths.is_empty() {
+                continue;
+            }
+            println!("{}: {}", title, paths.len());
+            if verbose {
+                paths.iter().for_each(|path| println!("  {}", path));
+            }
+        }
+
+        if !self.hooks.is_empty() {
+            println!("Imported hooks, run on every jump: {}", self.hooks.len());
+            self.hooks.iter().for_each(|hook| println!("  {}", hook));
+        }
+    }
+}
+
+//
+// ImportError
+//
+
+#[derive(Debug)]
+pub enum ImportError {
+    NoDefaultFile,
+    CdpathNotSet,
+    IoError(PathBuf, std::io::Error),
+    ParseError(String),
+}
+
+impl ImportError {
+    pub fn message(&self) -> String {
+        match self {
+            ImportError::NoDefaultFile => {
+                "Could not find the file to import from, please give its path".to_string()
+            }
+            ImportError::CdpathNotSet => "CDPATH is not set".to_string(),
+            ImportError::IoError(path, e) => format!("Could not read {}: {}", path.display(), e),
+            ImportError::ParseError(message) => format!("Could not parse the file: {}", message),
+        }
+    }
+}
+
+impl std::fmt::Display for ImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "oxmark - ImportError: {}", self.message())
+    }
+}
+
+impl std::error::Error for ImportError {}
@@ -0,0 +1,180 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use super::{ImportEntry, ImportError};
+use crate::{Bookmark, BookmarkKind};
+
+// Bumped when the exported fields change in a way older versions cannot read
+const EXPORT_VERSION: u32 = 1;
+
+// Tags are joined into a single CSV column with this separator.
+// Tags cannot contain commas, see `validate_tag`
+const CSV_TAG_SEPARATOR: &str = ",";
+
+// Variables may contain commas, but never newlines, see `validate_env`
+const CSV_ENV_SEPARATOR: &str = "\n";
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum ExportFormat {
+    Json,
+    Toml,
+    Csv,
+}
+
+// Top level of the JSON and TOML exports
+#[derive(Serialize, Deserialize)]
+struct Export {
+    version: u32,
+    #[serde(default)]
+    bookmarks: Vec<Bookmark>,
+}
+
+// A bookmark as a flat CSV row. Every field of `Bookmark` must have a column here
+#[derive(Serialize, Deserialize)]
+struct CsvRecord {
+    id: Option<i32>,
+    name: Option<String>,
+    path: Option<String>,
+    description: Option<String>,
+    tags: String,
+    folder: Option<String>,
+    visit_count: i32,
+    last_visited: Option<i64>,
+    created_at: Option<i64>,
+    updated_at: Option<i64>,
+    last_seen_existing: Option<i64>,
+    deleted_at: Option<i64>,
+    line: Option<u32>,
+    column: Option<u32>,
+    // Exports made before bookmark kinds existed have no such column
+    #[serde(default)]
+    kind: BookmarkKind,
+    pre_hook: Option<String>,
+    post_hook: Option<String>,
+    #[serde(default)]
+    env: String,
+    working_directory: Option<String>,
+}
+
+impl From<Bookmark> for CsvRecord {
+    fn from(bookmark: Bookmark) -> Self {
+        CsvRecord {
+            id: bookmark.id,
+            name: bookmark.name,
+            path: bookmark.path,
+            description: bookmark.description,
+            tags: bookmark.tags.join(CSV_TAG_SEPARATOR),
+            folder: bookmark.folder,
+            visit_count: bookmark.visit_count,
+            last_visited: bookmark.last_visited,
+            created_at: bookmark.created_at,
+            updated_at: bookmark.updated_at,
+            last_seen_existing: bookmark.last_seen_existing,
+            deleted_at: bookmark.deleted_at,
+            line: bookmark.line,
+            column: bookmark.column,
+            kind: bookmark.kind,
+            pre_hook: bookmark.pre_hook,
+            post_hook: bookmark.post_hook,
+            env: bookmark.env.join(CSV_ENV_SEPARATOR),
+            working_directory: bookmark.working_directory,
+        }
+    }
+}
+
+impl From<CsvRecord> for Bookmark {
+    fn from(record: CsvRecord) -> Self {
+        Bookmark {
+            id: record.id,
+            name: record.name,
+            path: record.path,
+            description: record.description,
+            tags: record
+                .tags
+                .split(CSV_TAG_SEPARATOR)
+                .filter(|tag| !tag.is_empty())
+                .map(|tag| tag.to_string())
+                .collect(),
+            folder: record.folder,
+            visit_count: record.visit_count,
+            last_visited: record.last_visited,
+            created_at: record.created_at,
+            updated_at: record.updated_at,
+            last_seen_existing: record.last_seen_existing,
+            deleted_at: record.deleted_at,
+            line: record.line,
+            column: record.column,
+            kind: record.kind,
+            pre_hook: record.pre_hook,
+            post_hook: record.post_hook,
+            env: record
+                .env
+                .split(CSV_ENV_SEPARATOR)
+                .filter(|entry| !entry.is_empty())
+                .map(|entry| entry.to_string())
+                .collect(),
+            working_directory: record.working_directory,
+        }
+    }
+}
+
+pub fn export(bookmarks: Vec<Bookmark>, format: ExportFormat) -> Result<String, String> {
+    match format {
+        ExportFormat::Json => serde_json::to_string_pretty(&Export {
+            version: EXPORT_VERSION,
+            bookmarks,
+        })
+        .map(|json| json + "\n")
+        .map_err(|e| e.to_string()),
+        ExportFormat::Toml => toml::to_string(&Export {
+            version: EXPORT_VERSION,
+            bookmarks,
+        })
+        .map_err(|e| e.to_string()),
+        ExportFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            for bookmark in bookmarks {
+                writer
+                    .serialize(CsvRecord::from(bookmark))
+                    .map_err(|e| e.to_string())?;
+            }
+            let bytes = writer.into_inner().map_err(|e| e.to_string())?;
+            Ok(String::from_utf8_lossy(&bytes).to_string())
+        }
+    }
+}
+
+pub fn parse(contents: &[u8], format: ExportFormat) -> Result<Vec<ImportEntry>, ImportError> {
+    let bookmarks = match format {
+        ExportFormat::Json => serde_json::from_slice::<Export>(contents)
+            .map_err(|e| ImportError::ParseError(e.to_string()))
+            .and_then(check_version)?,
+        ExportFormat::Toml => toml::from_str::<Export>(&String::from_utf8_lossy(contents))
+            .map_err(|e| ImportError::ParseError(e.to_string()))
+            .and_then(check_version)?,
+        ExportFormat::Csv => csv::Reader::from_reader(contents)
+            .deserialize::<CsvRecord>()
+            .map(|record| record.map(Bookmark::from))
+            .collect::<Result<Vec<Bookmark>, csv::Error>>()
+            .map_err(|e| ImportError::ParseError(e.to_string()))?,
+    };
+
+    Ok(bookmarks
+        .into_iter()
+        .filter(|bookmark| bookmark.path.is_some())
+        .map(|bookmark| ImportEntry {
+            bookmark,
+            score: None,
+        })
+        .collect())
+}
+
+fn check_version(export: Export) -> Result<Vec<Bookmark>, ImportError> {
+    if export.version > EXPORT_VERSION {
+        return Err(ImportError::ParseError(format!(
+            "export version {} is newer than the supported version {}",
+            export.version, EXPORT_VERSION
+        )));
+    }
+    Ok(export.bookmarks)
+}
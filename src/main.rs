@@ -1,37 +1,86 @@
-use std::io::{Read, Write};
+use std::path::PathBuf;
 
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
+mod config;
+mod fuzzy_filter;
+mod git;
+mod import;
+mod paths;
+mod profile;
+mod repair;
+mod service;
+mod shell;
 mod sqlite;
+mod store;
+mod time;
 mod ui;
 
 fn main() {
     let cli = Cli::parse();
 
-    let sqlite_service_result = sqlite::SqliteService::new();
-
-    if let Err(e) = sqlite_service_result {
+    let config = config::load().unwrap_or_else(|e| {
         println!("{}", e.message());
         std::process::exit(1);
+    });
+
+    // Profiles are managed before opening any database, so that a missing
+    // or broken profile can still be switched away from
+    if let Some(Commands::Profile { command }) = &cli.command {
+        let data_dir = paths::data_dir().unwrap_or_else(|e| {
+            println!("{}", e.message());
+            std::process::exit(1);
+        });
+        let profile_service = profile::ProfileService::new(data_dir, config.backend);
+
+        let result = match command {
+            ProfileCommands::List {} => {
+                profile_service.list();
+                Ok(())
+            }
+            ProfileCommands::Create { name } => profile_service.create(name.clone()),
+            ProfileCommands::Delete { name } => profile_service.delete(name.clone()),
+            ProfileCommands::Switch { name } => profile_service.switch(name.clone()),
+        };
+        result.unwrap_or_else(|e| {
+            println!("{}", e.message());
+            std::process::exit(1);
+        });
+        return;
     }
-    let sqlite_service = sqlite_service_result.unwrap();
 
-    let mut app = ui::App::new();
+    let location =
+        paths::database_location(cli.db, cli.profile, config.backend).unwrap_or_else(|e| {
+            println!("{}", e.message());
+            std::process::exit(1);
+        });
+
+    let store_result = store::open(location.backend, &location.path);
+
+    if let Err(e) = store_result {
+        println!("{}", e.message());
+        std::process::exit(1);
+    }
+    let bookmark_service = service::BookmarkService::new(store_result.unwrap());
 
     match cli.command {
         Some(Commands::Add {
             path,
             name,
             description,
+            folder,
+            command,
+            cwd,
         }) => {
-            sqlite_service
-                .create(path, name, description)
+            let kind = command.then_some(BookmarkKind::Command);
+            bookmark_service
+                .create(path, name, description, folder, kind, cwd)
                 .unwrap_or_else(|e| {
                     println!("{}", e.message());
                     std::process::exit(1);
                 });
         }
         Some(Commands::Delete { path }) => {
-            sqlite_service.delete(path).unwrap_or_else(|e| {
+            bookmark_service.delete(path).unwrap_or_else(|e| {
                 println!("{}", e.message());
                 std::process::exit(1);
             });
@@ -42,81 +91,305 @@ fn main() {
             name,
             description,
         }) => {
-            sqlite_service
+            bookmark_service
                 .update(id, path, name, description)
                 .unwrap_or_else(|e| {
                     println!("{}", e.message());
                     std::process::exit(1);
                 });
         }
-        Some(Commands::List { pathsonly }) => {
-            sqlite_service.get_all(pathsonly).unwrap_or_else(|e| {
+        Some(Commands::List {
+            pathsonly,
+            tags,
+            sort,
+            since,
+            before,
+        }) => {
+            let filter = BookmarkFilter {
+                tags,
+                since,
+                before,
+            };
+            bookmark_service
+                .get_all(pathsonly, filter, sort)
+                .unwrap_or_else(|e| {
+                    println!("{}", e.message());
+                    std::process::exit(1);
+                });
+        }
+        Some(Commands::Search {
+            query,
+            fts,
+            pathsonly,
+            limit,
+            first,
+            select_1,
+            exit_0,
+        }) => {
+            let query = query.join(" ");
+            let limit = if first { Some(1) } else { limit };
+            let bookmarks = bookmark_service
+                .find(&query, fts, limit)
+                .unwrap_or_else(|e| {
+                    println!("{}", e.message());
+                    std::process::exit(1);
+                });
+
+            // Like fzf, nothing matching is a failure with --exit-0, and --select-1
+            // only opens the interactive list when there is a choice to make
+            if bookmarks.is_empty() && exit_0 {
+                std::process::exit(1);
+            }
+            if select_1 && bookmarks.len() != 1 {
+                run_app(bookmark_service, location, Some(&query), false);
+            } else {
+                bookmark_service.print_bookmarks(&bookmarks, pathsonly);
+            }
+        }
+        Some(Commands::Import {
+            from,
+            file,
+            min_score,
+            on_conflict,
+            mode,
+            keep_missing,
+            dry_run,
+            with_hooks,
+            verbose,
+        }) => {
+            let entries = from.read_entries(file).unwrap_or_else(|e| {
                 println!("{}", e.message());
                 std::process::exit(1);
             });
+            let options = import::ImportOptions {
+                min_score,
+                on_conflict,
+                mode,
+                keep_missing: keep_missing || from.is_portable(),
+                dry_run,
+                with_hooks,
+            };
+            let summary = bookmark_service
+                .import(entries, options)
+                .unwrap_or_else(|e| {
+                    println!("{}", e.message());
+                    std::process::exit(1);
+                });
+            if dry_run {
+                println!("Dry run, nothing was changed");
+            }
+            summary.print(verbose || dry_run);
         }
-        Some(Commands::Command {}) => {
-            app.run(true).unwrap_or_else(|e| {
-                println!("{}", e);
+        Some(Commands::Export {
+            format,
+            output,
+            gtk,
+            prefix,
+        }) => {
+            let result = if gtk {
+                bookmark_service.export_gtk(prefix, output)
+            } else {
+                bookmark_service.export(format, output)
+            };
+            result.unwrap_or_else(|e| {
+                println!("{}", e.message());
+                std::process::exit(1);
+            });
+        }
+        Some(Commands::Hook { command }) => {
+            let result = match command {
+                HookCommands::Show { path } => bookmark_service.show_hooks(path),
+                HookCommands::Set {
+                    path,
+                    pre,
+                    post,
+                    env,
+                } => bookmark_service.set_hooks(path, pre, post, env),
+                HookCommands::Unset {
+                    path,
+                    pre,
+                    post,
+                    env,
+                } => bookmark_service.unset_hooks(path, pre, post, env),
+                HookCommands::Clear { path } => bookmark_service.clear_hooks(path),
+            };
+            result.unwrap_or_else(|e| {
+                println!("{}", e.message());
                 std::process::exit(1);
             });
         }
+        Some(Commands::Tag { command }) => {
+            let result = match command {
+                TagCommands::Add { path, tags } => bookmark_service.add_tags(path, tags),
+                TagCommands::Remove { path, tags } => bookmark_service.remove_tags(path, tags),
+                TagCommands::List { path } => bookmark_service.list_tags(path),
+                TagCommands::Rename { old_name, new_name } => {
+                    bookmark_service.rename_tag(old_name, new_name)
+                }
+            };
+            result.unwrap_or_else(|e| {
+                println!("{}", e.message());
+                std::process::exit(1);
+            });
+        }
+        Some(Commands::Folder { command }) => {
+            let result = match command {
+                FolderCommands::Create { path } => bookmark_service.create_folder(path),
+                FolderCommands::List {} => bookmark_service.list_folders(),
+                FolderCommands::Move {
+                    source,
+                    destination,
+                    folder: false,
+                } => bookmark_service.move_bookmark(source, destination),
+                FolderCommands::Move {
+                    source,
+                    destination,
+                    folder: true,
+                } => bookmark_service.move_folder(source, destination),
+            };
+            result.unwrap_or_else(|e| {
+                println!("{}", e.message());
+                std::process::exit(1);
+            });
+        }
+        Some(Commands::Trash { command }) => {
+            let result = match command {
+                TrashCommands::List {} => bookmark_service.list_trash(),
+                TrashCommands::Restore { path } => bookmark_service.restore(path),
+                TrashCommands::Empty {} => bookmark_service.empty_trash(),
+            };
+            result.unwrap_or_else(|e| {
+                println!("{}", e.message());
+                std::process::exit(1);
+            });
+        }
+        Some(Commands::Prune {
+            dry_run,
+            interactive,
+        }) => {
+            bookmark_service
+                .prune(dry_run, interactive)
+                .unwrap_or_else(|e| {
+                    println!("{}", e.message());
+                    std::process::exit(1);
+                });
+        }
+        Some(Commands::Relocate {
+            old_prefix,
+            new_prefix,
+            dry_run,
+        }) => {
+            bookmark_service
+                .relocate(old_prefix, new_prefix, dry_run)
+                .unwrap_or_else(|e| {
+                    println!("{}", e.message());
+                    std::process::exit(1);
+                });
+        }
+        Some(Commands::Repair {}) => {
+            bookmark_service.repair().unwrap_or_else(|e| {
+                println!("{}", e.message());
+                std::process::exit(1);
+            });
+        }
+        Some(Commands::History { id }) => {
+            bookmark_service.history(id).unwrap_or_else(|e| {
+                println!("{}", e.message());
+                std::process::exit(1);
+            });
+        }
+        Some(Commands::Revert { entry }) => {
+            bookmark_service.revert(entry).unwrap_or_else(|e| {
+                println!("{}", e.message());
+                std::process::exit(1);
+            });
+        }
+        Some(Commands::Undo {}) => {
+            bookmark_service.undo().unwrap_or_else(|e| {
+                println!("{}", e.message());
+                std::process::exit(1);
+            });
+        }
+        Some(Commands::Command { name: Some(name) }) => {
+            jump(bookmark_service, location, &name, true);
+        }
+        Some(Commands::Command { name: None }) => run_app(bookmark_service, location, None, true),
         Some(Commands::Init {}) => {
-            let function_string = "\n# Oxmark \nfunction ox() { if [ -z \"$1\" ]; then \"$(oxmark command)\"; else oxmark \"$@\"; fi }";
-
             // Look for .bashrc or .zshrc
             let home = std::env::var("HOME").unwrap_or("/home".to_string());
-            let bashrc = format!("{}/.bashrc", home);
-            let zshrc = format!("{}/.zshrc", home);
-            let bashrc_exists = std::path::Path::new(&bashrc).exists();
-            let zshrc_exists = std::path::Path::new(&zshrc).exists();
-
-            // TODO: Check if line already exists first
-
-            if bashrc_exists {
-                let mut file = std::fs::OpenOptions::new()
-                    .append(true)
-                    .read(true)
-                    .open(&bashrc)
-                    .unwrap();
-
-                let mut buf = String::new();
-                file.read_to_string(&mut buf).unwrap();
-
-                if !buf.contains(function_string) {
-                    println!("Adding line to .bashrc");
-                    file.write_all(function_string.as_bytes()).unwrap();
-                } else {
-                    println!("Line already exists in .bashrc");
-                }
-            }
-            if zshrc_exists {
-                let mut file = std::fs::OpenOptions::new()
-                    .append(true)
-                    .read(true)
-                    .open(&zshrc)
-                    .unwrap();
-
-                let mut buf = String::new();
-                file.read_to_string(&mut buf).unwrap();
-
-                if !buf.contains(function_string) {
-                    println!("Adding line to .zshrc");
-                    file.write_all(function_string.as_bytes()).unwrap();
-                } else {
-                    println!("Line already exists in .zshrc");
+
+            for file_name in [".bashrc", ".zshrc"] {
+                let path = std::path::Path::new(&home).join(file_name);
+                let Ok(contents) = std::fs::read_to_string(&path) else {
+                    continue;
+                };
+
+                let new_contents = shell::add_init_function(&contents);
+                if new_contents == contents {
+                    println!("The ox function is up to date in {}", file_name);
+                    continue;
                 }
+                println!("Writing the ox function to {}", file_name);
+                std::fs::write(&path, new_contents).unwrap_or_else(|e| {
+                    println!("Could not write {}: {}", file_name, e);
+                    std::process::exit(1);
+                });
             }
             println!(
                 "Done. After restarting your terminal use the 'ox' command to start using oxmark"
             );
         }
-        None => {
-            app.run(false).unwrap_or_else(|e| {
-                println!("{}", e);
-                std::process::exit(1);
-            });
+        // Handled before opening the database
+        Some(Commands::Profile { .. }) => {}
+        None => match cli.name {
+            Some(name) => jump(bookmark_service, location, &name, false),
+            None => run_app(bookmark_service, location, None, false),
+        },
+    }
+}
+
+// Go straight to the bookmark matching the name, or let the user pick among several.
+// Errors go to stderr, as the output is evaluated by the shell in command mode
+fn jump(
+    bookmark_service: service::BookmarkService,
+    location: paths::DatabaseLocation,
+    name: &str,
+    print_command: bool,
+) {
+    let result = match bookmark_service.find_by_name(name) {
+        Ok(service::NameMatch::Unique(bookmark)) => {
+            bookmark_service.visit(*bookmark, print_command)
         }
+        Ok(service::NameMatch::Ambiguous) => {
+            run_app(bookmark_service, location, Some(name), print_command);
+            Ok(())
+        }
+        Err(e) => Err(e),
+    };
+    result.unwrap_or_else(|e| {
+        eprintln!("{}", e.message());
+        std::process::exit(1);
+    });
+}
+
+// Open the interactive list on the store of the service, optionally with a query typed in.
+// Errors go to stderr in command mode
+fn run_app(
+    bookmark_service: service::BookmarkService,
+    location: paths::DatabaseLocation,
+    query: Option<&str>,
+    print_command: bool,
+) {
+    let mut app = ui::App::new(bookmark_service.into_store(), location);
+    if let Some(query) = query {
+        app.set_query(query);
+    }
+    if let Err(e) = app.run(print_command) {
+        match print_command {
+            true => eprintln!("{}", e),
+            false => println!("{}", e),
+        }
+        std::process::exit(1);
     }
 }
 
@@ -127,13 +400,21 @@ fn main() {
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 pub struct Cli {
-    /// Optional name to operate on
+    /// Print the path of the bookmark with this name, if it is the only match
     name: Option<String>,
 
     /// Turn debugging information on
     #[arg(short, long, action = clap::ArgAction::Count)]
     debug: u8,
 
+    /// Path of the database file, overrides the OXMARK_DB environment variable
+    #[arg(long, global = true)]
+    db: Option<PathBuf>,
+
+    /// Use the bookmarks of this profile instead of the active one
+    #[arg(long, global = true)]
+    profile: Option<String>,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -142,7 +423,9 @@ pub struct Cli {
 pub enum Commands {
     // Add a bookmark
     Add {
-        /// The path of the bookmark
+        /// The path of the bookmark, optionally followed by a line and column such as src/main.rs:120:5,
+        /// a URL such as http://localhost:3000, a remote directory such as build01:/srv/app,
+        /// or a shell command with --command
         path: String,
 
         /// The name of the bookmark
@@ -150,9 +433,21 @@ pub enum Commands {
 
         /// The description of the bookmark
         description: Option<String>,
+
+        /// The folder to put the bookmark in, such as work/backend
+        #[arg(short, long)]
+        folder: Option<String>,
+
+        /// Save the path argument as a shell command, run when the bookmark is selected
+        #[arg(short, long, action = clap::ArgAction::SetTrue)]
+        command: bool,
+
+        /// The directory the command is run from
+        #[arg(long, requires = "command")]
+        cwd: Option<String>,
     },
 
-    // Delete a bookmark
+    // Move a bookmark to the trash
     Delete {
         /// The path of the bookmark
         path: String,
@@ -179,45 +474,570 @@ pub enum Commands {
     List {
         #[arg(short, long, action = clap::ArgAction::SetTrue)]
         pathsonly: bool,
+
+        /// Only list bookmarks with this tag, can be repeated
+        #[arg(short, long = "tag")]
+        tags: Vec<String>,
+
+        /// The order to list the bookmarks in
+        #[arg(short, long, value_enum, default_value_t = SortOrder::Id)]
+        sort: SortOrder,
+
+        /// Only list bookmarks created since this date (YYYY-MM-DD[ HH:MM]) or duration ago (7d)
+        #[arg(long, value_parser = time::parse)]
+        since: Option<i64>,
+
+        /// Only list bookmarks created before this date (YYYY-MM-DD[ HH:MM]) or duration ago (7d)
+        #[arg(long, value_parser = time::parse)]
+        before: Option<i64>,
+    },
+
+    // Search bookmarks
+    Search {
+        /// The search query
+        #[arg(required = true)]
+        query: Vec<String>,
+
+        /// Search the name, path and description with full-text search, ranking the best matches first
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        fts: bool,
+
+        /// Print only the paths instead of the full records
+        #[arg(short, long, action = clap::ArgAction::SetTrue)]
+        pathsonly: bool,
+
+        /// Print at most this many matches
+        #[arg(short = 'n', long)]
+        limit: Option<usize>,
+
+        /// Print only the best match
+        #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with = "limit")]
+        first: bool,
+
+        /// Print the match if there is only one, otherwise pick from the interactive list
+        #[arg(short = '1', long, action = clap::ArgAction::SetTrue)]
+        select_1: bool,
+
+        /// Exit with status 1 when nothing matches, instead of printing nothing or opening the list
+        #[arg(short = '0', long, action = clap::ArgAction::SetTrue)]
+        exit_0: bool,
+    },
+
+    // Import bookmarks from another tool or from an export
+    Import {
+        /// The tool to import from, or the format of an export
+        #[arg(long, value_enum)]
+        from: import::ImportSource,
+
+        /// The file to import, defaults to the tool's own data file
+        file: Option<PathBuf>,
+
+        /// Skip entries ranked lower than this by the tool
+        #[arg(long)]
+        min_score: Option<f64>,
+
+        /// What to do when the name of an imported bookmark is already used
+        #[arg(long, value_enum, default_value_t = import::NameConflict::Rename)]
+        on_conflict: import::NameConflict,
+
+        /// Add to the existing bookmarks, or delete them all first
+        #[arg(long, value_enum, default_value_t = import::ImportMode::Merge)]
+        mode: import::ImportMode,
+
+        /// Import paths that do not exist on this machine, always done for exports
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        keep_missing: bool,
+
+        /// Show what would be imported without changing anything
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        dry_run: bool,
+
+        /// Keep the hooks and variables of exported bookmarks, which run on every jump
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        with_hooks: bool,
+
+        /// List every imported and skipped path
+        #[arg(short, long, action = clap::ArgAction::SetTrue)]
+        verbose: bool,
+    },
+
+    // Export all bookmarks
+    Export {
+        /// The format of the export
+        #[arg(long, value_enum, default_value_t = import::ExportFormat::Json)]
+        format: import::ExportFormat,
+
+        /// The file to write to, defaults to the standard output, or the GTK bookmarks file with --gtk
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Rewrite the sidebar bookmarks of GTK file managers, keeping their non-file entries
+        #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with = "format")]
+        gtk: bool,
+
+        /// Only export bookmarks under this directory
+        #[arg(long, requires = "gtk")]
+        prefix: Option<String>,
+    },
+
+    // Manage bookmark tags
+    Tag {
+        #[command(subcommand)]
+        command: TagCommands,
+    },
+
+    // Manage the commands and variables applied when jumping to a bookmark
+    Hook {
+        #[command(subcommand)]
+        command: HookCommands,
+    },
+
+    // Organize bookmarks into nested folders
+    Folder {
+        #[command(subcommand)]
+        command: FolderCommands,
+    },
+
+    // Manage deleted bookmarks
+    Trash {
+        #[command(subcommand)]
+        command: TrashCommands,
+    },
+
+    // Move the bookmarks whose target no longer exists to the trash
+    Prune {
+        /// Only list the bookmarks that would be moved to the trash
+        #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with = "interactive")]
+        dry_run: bool,
+
+        /// Ask before moving each bookmark
+        #[arg(short, long, action = clap::ArgAction::SetTrue)]
+        interactive: bool,
+    },
+
+    // Look for the new location of bookmarks whose target was moved
+    Repair {},
+
+    // Rewrite the paths of all bookmarks under a directory that was moved or renamed
+    Relocate {
+        /// The directory the bookmarks are in now, such as /home/old-user/work
+        old_prefix: String,
+
+        /// The directory to move them to
+        new_prefix: String,
+
+        /// Only show the new paths
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        dry_run: bool,
+    },
+
+    // Revert the last creation, update or deletion of a bookmark
+    Undo {},
+
+    // Show the changes made to bookmarks, oldest first
+    History {
+        /// Only show the changes made to the bookmark with this id
+        #[arg(long)]
+        id: Option<i32>,
+    },
+
+    // Bring a bookmark back to its state right after a change from the history
+    Revert {
+        /// The number of the history entry
+        entry: i32,
+    },
+
+    // Manage the profiles holding separate sets of bookmarks
+    Profile {
+        #[command(subcommand)]
+        command: ProfileCommands,
     },
 
     // Print the command for selected bookmark
-    Command {},
+    Command {
+        /// Go straight to the bookmark with this name, if it is the only match
+        name: Option<String>,
+    },
 
     // Initialize oxmark
     Init {},
 }
 
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Subcommand)]
+pub enum HookCommands {
+    // Show the hooks and variables of a bookmark
+    Show {
+        /// The path of the bookmark
+        path: String,
+    },
+
+    // Set hooks, and add or replace variables
+    Set {
+        /// The path of the bookmark
+        path: String,
+
+        /// Command run before jumping, such as 'deactivate'
+        #[arg(long)]
+        pre: Option<String>,
+
+        /// Command run after jumping, such as 'source .venv/bin/activate'
+        #[arg(long)]
+        post: Option<String>,
+
+        /// Variable exported when jumping, as KEY=value. Can be repeated
+        #[arg(short, long)]
+        env: Vec<String>,
+    },
+
+    // Remove hooks or variables
+    Unset {
+        /// The path of the bookmark
+        path: String,
+
+        /// Remove the command run before jumping
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        pre: bool,
+
+        /// Remove the command run after jumping
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        post: bool,
+
+        /// Name of a variable to remove. Can be repeated
+        #[arg(short, long)]
+        env: Vec<String>,
+    },
+
+    // Remove all hooks and variables
+    Clear {
+        /// The path of the bookmark
+        path: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum TagCommands {
+    // Add tags to a bookmark
+    Add {
+        /// The path of the bookmark
+        path: String,
+
+        /// The tags to add
+        #[arg(required = true)]
+        tags: Vec<String>,
+    },
+
+    // Remove tags from a bookmark
+    Remove {
+        /// The path of the bookmark
+        path: String,
+
+        /// The tags to remove
+        #[arg(required = true)]
+        tags: Vec<String>,
+    },
+
+    // List all tags, or the tags of a bookmark
+    List {
+        /// The path of the bookmark
+        path: Option<String>,
+    },
+
+    // Rename a tag on every bookmark
+    Rename {
+        /// The current name of the tag
+        old_name: String,
+
+        /// The new name of the tag
+        new_name: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum FolderCommands {
+    // Create a folder, along with its missing parents
+    Create {
+        /// The path of the folder, such as work/backend
+        path: String,
+    },
+
+    // Move a bookmark, or a folder with --folder, into another folder
+    Move {
+        /// The path of the bookmark, or of the folder with --folder
+        source: String,
+
+        /// The folder to move into, the top level if omitted
+        destination: Option<String>,
+
+        /// Move a folder instead of a bookmark
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        folder: bool,
+    },
+
+    // List the folders as a tree, with the number of bookmarks in each
+    List {},
+}
+
+#[derive(Subcommand)]
+pub enum TrashCommands {
+    // List deleted bookmarks, most recently deleted first
+    List {},
+
+    // Restore a deleted bookmark
+    Restore {
+        /// The path of the bookmark
+        path: String,
+    },
+
+    // Permanently delete the bookmarks in the trash, this cannot be undone
+    Empty {},
+}
+
+#[derive(Subcommand)]
+pub enum ProfileCommands {
+    // List all profiles, marking the active one
+    List {},
+
+    // Create a new profile
+    Create {
+        /// The name of the profile
+        name: String,
+    },
+
+    // Delete a profile and all of its bookmarks
+    Delete {
+        /// The name of the profile
+        name: String,
+    },
+
+    // Make a profile the active one
+    Switch {
+        /// The name of the profile
+        name: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum SortOrder {
+    // Insertion order
+    Id,
+    Name,
+    // Most frequently and recently visited first
+    Frecency,
+    // Most recently created first
+    Created,
+    // Most recently updated first
+    Updated,
+}
+
+impl SortOrder {
+    pub fn sort(&self, bookmarks: &mut [Bookmark]) {
+        match self {
+            SortOrder::Id => bookmarks.sort_by_key(|bookmark| bookmark.id),
+            SortOrder::Name => bookmarks.sort_by_key(|bookmark| bookmark.name.clone()),
+            SortOrder::Frecency => {
+                let now = time::now();
+                // Stable sort, so bookmarks with the same score keep their insertion order
+                bookmarks.sort_by(|a, b| b.frecency(now).total_cmp(&a.frecency(now)));
+            }
+            SortOrder::Created => {
+                bookmarks.sort_by_key(|bookmark| std::cmp::Reverse(bookmark.created_at))
+            }
+            SortOrder::Updated => {
+                bookmarks.sort_by_key(|bookmark| std::cmp::Reverse(bookmark.updated_at))
+            }
+        }
+    }
+}
+
+// Criteria used to narrow down listed bookmarks
+#[derive(Debug, Default)]
+pub struct BookmarkFilter {
+    // Bookmarks must have all of these tags
+    pub tags: Vec<String>,
+    // Bookmarks must have been created at or after this time
+    pub since: Option<i64>,
+    // Bookmarks must have been created before this time
+    pub before: Option<i64>,
+}
+
+impl BookmarkFilter {
+    pub fn matches(&self, bookmark: &Bookmark) -> bool {
+        if !self.tags.iter().all(|tag| bookmark.tags.contains(tag)) {
+            return false;
+        }
+
+        // Bookmarks with an unknown creation time never match a time filter
+        if let Some(since) = self.since {
+            if bookmark
+                .created_at
+                .is_none_or(|created_at| created_at < since)
+            {
+                return false;
+            }
+        }
+        if let Some(before) = self.before {
+            if bookmark
+                .created_at
+                .is_none_or(|created_at| created_at >= before)
+            {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+// Exports serialize every field, missing fields are left empty on import
+#[derive(Debug, Clone, Default, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 pub struct Bookmark {
     pub id: Option<i32>,
     pub name: Option<String>,
     pub path: Option<String>,
     pub description: Option<String>,
+    pub tags: Vec<String>,
+    // Full path of the folder holding the bookmark, such as "work/backend"
+    pub folder: Option<String>,
+    pub visit_count: i32,
+    pub last_visited: Option<i64>,
+    pub created_at: Option<i64>,
+    pub updated_at: Option<i64>,
+    pub last_seen_existing: Option<i64>,
+    // Set while the bookmark is in the trash
+    pub deleted_at: Option<i64>,
+    // Position in a bookmarked file, both starting at 1
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+    // What the path points to, which decides how the bookmark is opened
+    pub kind: BookmarkKind,
+    // Shell commands run before and after jumping to the bookmark
+    pub pre_hook: Option<String>,
+    pub post_hook: Option<String>,
+    // Variables exported when jumping, as "KEY=value" in the order they were set
+    pub env: Vec<String>,
+    // Directory command bookmarks are run from
+    pub working_directory: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BookmarkKind {
+    // A local file or directory
+    #[default]
+    Path,
+    // A web address, kept exactly as it was given
+    Url,
+    // A shell command, optionally run from a working directory
+    Command,
+    // A directory on another machine as "host:path", opened over SSH
+    Remote,
+}
+
+impl BookmarkKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BookmarkKind::Path => "path",
+            BookmarkKind::Url => "url",
+            BookmarkKind::Command => "command",
+            BookmarkKind::Remote => "remote",
+        }
+    }
+
+    pub fn parse(kind: &str) -> Option<BookmarkKind> {
+        match kind {
+            "path" => Some(BookmarkKind::Path),
+            "url" => Some(BookmarkKind::Url),
+            "command" => Some(BookmarkKind::Command),
+            "remote" => Some(BookmarkKind::Remote),
+            _ => None,
+        }
+    }
+
+    // Addresses such as https://example.com or http://localhost:3000. Inputs without
+    // "://" are paths, since "notes.md:12" would otherwise parse as a URL.
+    // Targets such as build01:/srv/app are remote
+    pub fn of_input(input: &str) -> BookmarkKind {
+        let is_url = input.contains("://")
+            && url::Url::parse(input).is_ok_and(|url| url.has_host() && url.scheme() != "file");
+        if is_url {
+            BookmarkKind::Url
+        } else if Bookmark::split_remote(input).is_some() {
+            BookmarkKind::Remote
+        } else {
+            BookmarkKind::Path
+        }
+    }
 }
 impl Bookmark {
     pub fn new(
         name: Option<String>,
         path: Option<String>,
         description: Option<String>,
-    ) -> Bookmark {
-        // Canonicalize the path, meaning that it will be an absolute path
+    ) -> std::io::Result<Bookmark> {
+        // Canonicalize the path, meaning that it will be an absolute path.
+        // This fails when nothing exists at the path
         let abs_path = if let Some(path) = path {
             std::path::PathBuf::from(path)
-                .canonicalize()
-                .unwrap()
-                .to_str()
-                .unwrap()
+                .canonicalize()?
+                .to_string_lossy()
                 .to_string()
         } else {
             "".to_string()
         };
 
-        Bookmark {
+        Ok(Bookmark {
             id: None,
             name,
             path: Some(abs_path),
             description,
+            ..Default::default()
+        })
+    }
+
+    // URLs are not canonicalized, they are stored as given
+    pub fn new_url(name: Option<String>, url: String, description: Option<String>) -> Bookmark {
+        Bookmark {
+            id: None,
+            name,
+            path: Some(url),
+            description,
+            kind: BookmarkKind::Url,
+            ..Default::default()
+        }
+    }
+
+    // The command is stored in place of the path, so that it is matched and shown the same way
+    pub fn new_command(
+        name: Option<String>,
+        command: String,
+        description: Option<String>,
+        working_directory: Option<String>,
+    ) -> Bookmark {
+        Bookmark {
+            id: None,
+            name,
+            path: Some(command),
+            description,
+            kind: BookmarkKind::Command,
+            working_directory,
+            ..Default::default()
+        }
+    }
+
+    // Remote targets are not canonicalized, the host may be an alias from ~/.ssh/config
+    pub fn new_remote(
+        name: Option<String>,
+        target: String,
+        description: Option<String>,
+    ) -> Bookmark {
+        Bookmark {
+            id: None,
+            name,
+            path: Some(target),
+            description,
+            kind: BookmarkKind::Remote,
+            ..Default::default()
         }
     }
 
@@ -238,29 +1058,113 @@ impl Bookmark {
         }
     }
 
-    pub fn to_string(&self) -> String {
-        let id = if self.id.is_none() {
-            "None".to_string()
+    // The position as "line:column", or only the line when there is no column
+    pub fn position(&self) -> Option<String> {
+        let line = self.line?;
+        Some(match self.column {
+            Some(column) => format!("{}:{}", line, column),
+            None => line.to_string(),
+        })
+    }
+
+    // Split "path:line:column" or "path:line" given on the command line.
+    // Paths that exist as given are kept whole, even if they end with ":number"
+    pub fn split_position(input: &str) -> (String, Option<u32>, Option<u32>) {
+        if std::path::Path::new(input).exists() {
+            return (input.to_string(), None, None);
+        }
+
+        let parse = |number: &str| number.parse::<u32>().ok().filter(|number| *number > 0);
+        let parts: Vec<&str> = input.rsplitn(3, ':').collect();
+        match parts.as_slice() {
+            [column, line, path] if parse(line).is_some() && parse(column).is_some() => {
+                (path.to_string(), parse(line), parse(column))
+            }
+            [line, ..] if parse(line).is_some() => {
+                let path = &input[..input.len() - line.len() - 1];
+                (path.to_string(), parse(line), None)
+            }
+            _ => (input.to_string(), None, None),
+        }
+    }
+
+    // Split "host:path" into the host and the path on it. The path must be absolute or
+    // start with "~", so that "notes.md:12" stays local, and paths that exist locally
+    // are kept whole. The host may hold a user, as in deploy@build01
+    pub fn split_remote(input: &str) -> Option<(String, String)> {
+        if std::path::Path::new(input).exists() {
+            return None;
+        }
+
+        let (host, path) = input.split_once(':')?;
+        let is_host = !host.is_empty()
+            && !host.contains('/')
+            && !host.chars().any(char::is_whitespace)
+            && !host.starts_with('-');
+        let is_path = path.starts_with('/') || path.starts_with('~');
+        (is_host && is_path).then(|| (host.to_string(), path.to_string()))
+    }
+
+    // The host and path of a remote bookmark, which were checked when it was added
+    pub fn remote_target(&self) -> Option<(String, String)> {
+        if self.kind != BookmarkKind::Remote {
+            return None;
+        }
+        let (host, path) = self.path.as_deref()?.split_once(':')?;
+        Some((host.to_string(), path.to_string()))
+    }
+
+    // Visit count weighted by how recently the bookmark was visited, like zoxide does
+    pub fn frecency(&self, now: i64) -> f64 {
+        let Some(last_visited) = self.last_visited else {
+            return 0.0;
+        };
+
+        let age = now - last_visited;
+        let weight = if age < 60 * 60 {
+            4.0
+        } else if age < 60 * 60 * 24 {
+            2.0
+        } else if age < 60 * 60 * 24 * 7 {
+            0.5
         } else {
-            self.id.unwrap().to_string()
+            0.25
         };
-        let name = self.name.clone().unwrap_or("None".to_string());
-        let path = self.path.clone().unwrap_or("None".to_string());
-        let description = self.description.clone().unwrap_or("None".to_string());
-        format!(
-            "id: {}, name: {}, path: {}, description: {}",
-            id, name, path, description
-        )
+
+        self.visit_count as f64 * weight
     }
 }
 
-impl Default for Bookmark {
-    fn default() -> Self {
-        Bookmark {
-            id: None,
-            name: None,
-            path: None,
-            description: None,
+impl std::fmt::Display for Bookmark {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let id = self
+            .id
+            .map(|id| id.to_string())
+            .unwrap_or("None".to_string());
+        let name = self.name.clone().unwrap_or("None".to_string());
+        let mut path = self.path.clone().unwrap_or("None".to_string());
+        if let Some(position) = self.position() {
+            path = format!("{}:{}", path, position);
         }
+        let description = self.description.clone().unwrap_or("None".to_string());
+        let tags = if self.tags.is_empty() {
+            "None".to_string()
+        } else {
+            self.tags.join(",")
+        };
+        let folder = self.folder.clone().unwrap_or("None".to_string());
+        let created_at = self
+            .created_at
+            .map(time::format)
+            .unwrap_or("None".to_string());
+        let updated_at = self
+            .updated_at
+            .map(time::format)
+            .unwrap_or("None".to_string());
+        write!(
+            f,
+            "id: {}, name: {}, path: {}, description: {}, tags: {}, folder: {}, created: {}, updated: {}",
+            id, name, path, description, tags, folder, created_at, updated_at
+        )
     }
 }
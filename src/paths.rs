@@ -0,0 +1,133 @@
+use std::path::PathBuf;
+
+use crate::config::Backend;
+use crate::profile;
+
+const DATA_DIR_NAME: &str = "oxmark";
+const DEFAULT_DATA_HOME: &str = ".local/share";
+const DEFAULT_CONFIG_HOME: &str = ".config";
+
+// Environment variable overriding the database location
+const DATABASE_ENV: &str = "OXMARK_DB";
+
+#[derive(Debug, Clone)]
+pub struct DatabaseLocation {
+    pub path: PathBuf,
+    pub backend: Backend,
+
+    // The profile the database belongs to, if it was not given explicitly
+    pub profile: Option<String>,
+}
+
+// Resolve the database path, in order of precedence from the --db flag,
+// the OXMARK_DB environment variable and the profile's database in the XDG data directory
+pub fn database_location(
+    cli_db: Option<PathBuf>,
+    cli_profile: Option<String>,
+    backend: Backend,
+) -> Result<DatabaseLocation, PathsError> {
+    let explicit_path = cli_db.or(non_empty_env(DATABASE_ENV).map(PathBuf::from));
+
+    if let Some(path) = explicit_path {
+        if cli_profile.is_some() {
+            return Err(PathsError::ProfileWithExplicitDatabase);
+        }
+        return Ok(DatabaseLocation {
+            path,
+            backend,
+            profile: None,
+        });
+    }
+
+    let data_dir = data_dir()?;
+
+    if let Some(name) = &cli_profile {
+        profile::validate_name(name).map_err(|e| PathsError::InvalidProfile(e.message()))?;
+    }
+
+    let profile = cli_profile.unwrap_or(profile::active_profile(&data_dir, backend));
+    if !profile::exists(&data_dir, &profile, backend) {
+        return Err(PathsError::ProfileNotFound(profile));
+    }
+
+    Ok(DatabaseLocation {
+        path: profile::database_path(&data_dir, &profile, backend),
+        backend,
+        profile: Some(profile),
+    })
+}
+
+// $XDG_DATA_HOME/oxmark, falling back to $HOME/.local/share/oxmark
+pub fn data_dir() -> Result<PathBuf, PathsError> {
+    match xdg_data_home() {
+        Some(data_home) => Ok(data_home.join(DATA_DIR_NAME)),
+        None => Err(PathsError::DataDirNotFound),
+    }
+}
+
+// $XDG_DATA_HOME, falling back to $HOME/.local/share
+pub fn xdg_data_home() -> Option<PathBuf> {
+    // Relative paths in XDG variables are invalid and must be ignored
+    let xdg_data_home = non_empty_env("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute());
+
+    xdg_data_home.or(home_dir().map(|home| home.join(DEFAULT_DATA_HOME)))
+}
+
+// $XDG_CONFIG_HOME, falling back to $HOME/.config
+pub fn xdg_config_home() -> Option<PathBuf> {
+    let xdg_config_home = non_empty_env("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute());
+
+    xdg_config_home.or(home_dir().map(|home| home.join(DEFAULT_CONFIG_HOME)))
+}
+
+pub fn home_dir() -> Option<PathBuf> {
+    non_empty_env("HOME").map(PathBuf::from)
+}
+
+pub fn non_empty_env(key: &str) -> Option<String> {
+    std::env::var(key).ok().filter(|value| !value.is_empty())
+}
+
+//
+// PathsError
+//
+
+#[derive(Debug)]
+pub enum PathsError {
+    DataDirNotFound,
+    ProfileNotFound(String),
+    InvalidProfile(String),
+    ProfileWithExplicitDatabase,
+}
+
+impl PathsError {
+    pub fn message(&self) -> String {
+        match self {
+            PathsError::DataDirNotFound => format!(
+                "Could not find the database location: use --db, set {} or set XDG_DATA_HOME or HOME",
+                DATABASE_ENV
+            ),
+            PathsError::ProfileNotFound(name) => format!(
+                "Profile {} not found, create it with 'oxmark profile create {}'",
+                name, name
+            ),
+            PathsError::InvalidProfile(message) => message.clone(),
+            PathsError::ProfileWithExplicitDatabase => format!(
+                "--profile cannot be used together with --db or {}",
+                DATABASE_ENV
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for PathsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "oxmark - PathsError: {}", self.message())
+    }
+}
+
+impl std::error::Error for PathsError {}
@@ -0,0 +1,199 @@
+use std::path::{Path, PathBuf};
+
+use crate::config::Backend;
+
+// The default profile uses the database oxmark has always used
+pub const DEFAULT_PROFILE: &str = "default";
+pub const DEFAULT_DATABASE_NAME: &str = "oxmark.db";
+
+// Other profiles each have their own database in this directory, with the extension of the backend
+const PROFILES_DIR: &str = "profiles";
+
+// File holding the name of the profile selected with `oxmark profile switch`
+const ACTIVE_PROFILE_FILE: &str = "active_profile";
+
+pub fn database_path(data_dir: &Path, name: &str, backend: Backend) -> PathBuf {
+    if name == DEFAULT_PROFILE {
+        return data_dir
+            .join(DEFAULT_DATABASE_NAME)
+            .with_extension(backend.extension());
+    }
+    data_dir
+        .join(PROFILES_DIR)
+        .join(name)
+        .with_extension(backend.extension())
+}
+
+// Invalid names never exist, they could point outside the profiles directory
+pub fn exists(data_dir: &Path, name: &str, backend: Backend) -> bool {
+    name == DEFAULT_PROFILE
+        || (validate_name(name).is_ok() && database_path(data_dir, name, backend).exists())
+}
+
+pub fn active_profile(data_dir: &Path, backend: Backend) -> String {
+    std::fs::read_to_string(data_dir.join(ACTIVE_PROFILE_FILE))
+        .map(|name| name.trim().to_string())
+        .ok()
+        .filter(|name| exists(data_dir, name, backend))
+        .unwrap_or(DEFAULT_PROFILE.to_string())
+}
+
+// All the profiles, sorted by name with the default profile first
+pub fn list(data_dir: &Path, backend: Backend) -> Vec<String> {
+    let mut profiles: Vec<String> = std::fs::read_dir(data_dir.join(PROFILES_DIR))
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    path.extension().and_then(|extension| extension.to_str())
+                        == Some(backend.extension())
+                })
+                .filter_map(|path| Some(path.file_stem()?.to_string_lossy().to_string()))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    profiles.sort();
+    profiles.insert(0, DEFAULT_PROFILE.to_string());
+    profiles
+}
+
+/*
+ *
+ * Profile Service
+ *
+ */
+
+pub struct ProfileService {
+    data_dir: PathBuf,
+    backend: Backend,
+}
+
+impl ProfileService {
+    pub fn new(data_dir: PathBuf, backend: Backend) -> ProfileService {
+        ProfileService { data_dir, backend }
+    }
+
+    pub fn list(&self) {
+        let active_profile = active_profile(&self.data_dir, self.backend);
+
+        list(&self.data_dir, self.backend).iter().for_each(|name| {
+            let marker = if *name == active_profile { "*" } else { " " };
+            println!("{} {}", marker, name);
+        });
+    }
+
+    pub fn create(&self, name: String) -> Result<(), ProfileError> {
+        validate_name(&name)?;
+        if exists(&self.data_dir, &name, self.backend) {
+            return Err(ProfileError::AlreadyExists(name));
+        }
+
+        // Opening the store creates and migrates the database
+        let path = database_path(&self.data_dir, &name, self.backend);
+        if let Err(e) = crate::store::open(self.backend, &path) {
+            return Err(ProfileError::DatabaseError(e.message()));
+        }
+
+        println!("Created profile {}", name);
+        Ok(())
+    }
+
+    pub fn delete(&self, name: String) -> Result<(), ProfileError> {
+        validate_name(&name)?;
+        if name == DEFAULT_PROFILE {
+            return Err(ProfileError::CannotDeleteDefault);
+        }
+        if !exists(&self.data_dir, &name, self.backend) {
+            return Err(ProfileError::NotFound(name));
+        }
+        if active_profile(&self.data_dir, self.backend) == name {
+            return Err(ProfileError::CannotDeleteActive(name));
+        }
+
+        let result = crate::store::remove(
+            self.backend,
+            &database_path(&self.data_dir, &name, self.backend),
+        );
+        if let Err(e) = result {
+            return Err(ProfileError::IoError(e));
+        }
+
+        println!("Deleted profile {}", name);
+        Ok(())
+    }
+
+    pub fn switch(&self, name: String) -> Result<(), ProfileError> {
+        validate_name(&name)?;
+        if !exists(&self.data_dir, &name, self.backend) {
+            return Err(ProfileError::NotFound(name));
+        }
+
+        let result = std::fs::create_dir_all(&self.data_dir)
+            .and_then(|_| std::fs::write(self.data_dir.join(ACTIVE_PROFILE_FILE), &name));
+        if let Err(e) = result {
+            return Err(ProfileError::IoError(e));
+        }
+
+        println!("Switched to profile {}", name);
+        Ok(())
+    }
+}
+
+// Profile names are used as file names
+pub fn validate_name(name: &str) -> Result<(), ProfileError> {
+    let is_valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !is_valid {
+        return Err(ProfileError::InvalidName(name.to_string()));
+    }
+    Ok(())
+}
+
+//
+// ProfileError
+//
+
+#[derive(Debug)]
+pub enum ProfileError {
+    NotFound(String),
+    AlreadyExists(String),
+    InvalidName(String),
+    CannotDeleteDefault,
+    CannotDeleteActive(String),
+    DatabaseError(String),
+    IoError(std::io::Error),
+}
+
+impl ProfileError {
+    pub fn message(&self) -> String {
+        match self {
+            ProfileError::NotFound(name) => format!("Profile {} not found", name),
+            ProfileError::AlreadyExists(name) => format!("Profile {} already exists", name),
+            ProfileError::InvalidName(name) => format!(
+                "Invalid profile name '{}': only letters, digits, '-' and '_' are allowed",
+                name
+            ),
+            ProfileError::CannotDeleteDefault => {
+                format!("The {} profile cannot be deleted", DEFAULT_PROFILE)
+            }
+            ProfileError::CannotDeleteActive(name) => format!(
+                "Profile {} is the active profile, switch to another profile first",
+                name
+            ),
+            ProfileError::DatabaseError(message) => message.clone(),
+            ProfileError::IoError(e) => format!("IO error: {}", e),
+        }
+    }
+}
+
+impl std::fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "oxmark - ProfileError: {}", self.message())
+    }
+}
+
+impl std::error::Error for ProfileError {}
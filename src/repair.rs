@@ -0,0 +1,123 @@
+// Finding the new location of bookmarks whose target was moved
+
+use std::collections::VecDeque;
+use std::io::{BufRead, Write};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+// How many directories below the search root are looked into
+const MAX_SEARCH_DEPTH: usize = 3;
+
+// Stop searching after this many entries, so that a root such as / stays fast
+const MAX_SEARCHED_ENTRIES: usize = 20_000;
+
+// Identifies a file across renames on the same filesystem
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FileId {
+    pub inode: u64,
+    pub device: u64,
+}
+
+impl FileId {
+    pub fn of(path: &Path) -> Option<FileId> {
+        std::fs::metadata(path).ok().map(|metadata| FileId {
+            inode: metadata.ino(),
+            device: metadata.dev(),
+        })
+    }
+}
+
+// Places the missing path may have moved to, nearest first. The search starts one
+// level above the closest directory that still exists, which covers renames next to
+// the target as well as moves into a sibling directory. Entries match by basename,
+// or by the inode and device the target was last seen with
+pub fn find_candidates(path: &Path, file_id: Option<FileId>) -> Vec<PathBuf> {
+    let Some(basename) = path.file_name() else {
+        return vec![];
+    };
+
+    let Some(existing_dir) = path.ancestors().skip(1).find(|dir| dir.is_dir()) else {
+        return vec![];
+    };
+    let root = existing_dir.parent().unwrap_or(existing_dir);
+
+    let mut candidates = Vec::new();
+    let mut searched_entries = 0;
+    let mut queue = VecDeque::from([(root.to_path_buf(), 0)]);
+
+    while let Some((dir, depth)) = queue.pop_front() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+
+        for entry in entries.flatten() {
+            searched_entries += 1;
+            if searched_entries > MAX_SEARCHED_ENTRIES {
+                return candidates;
+            }
+
+            let entry_path = entry.path();
+            let same_file = file_id.is_some_and(|file_id| FileId::of(&entry_path) == Some(file_id));
+            if entry.file_name() == basename || same_file {
+                candidates.push(entry_path.clone());
+            }
+
+            // Symbolic links are not followed, and hidden directories are skipped
+            let is_dir = entry.file_type().is_ok_and(|file_type| file_type.is_dir());
+            let is_hidden = entry.file_name().to_string_lossy().starts_with('.');
+            if is_dir && !is_hidden && depth < MAX_SEARCH_DEPTH {
+                queue.push_back((entry_path, depth + 1));
+            }
+        }
+    }
+
+    candidates
+}
+
+/*
+ *
+ * Prompts
+ *
+ */
+
+fn read_answer(question: &str) -> String {
+    print!("{} ", question);
+    std::io::stdout().flush().unwrap_or_default();
+
+    let mut answer = String::new();
+    std::io::stdin()
+        .lock()
+        .read_line(&mut answer)
+        .unwrap_or_default();
+    answer.trim().to_lowercase()
+}
+
+// Anything but yes is a no, including an empty answer
+pub fn confirm(question: &str) -> bool {
+    matches!(
+        read_answer(&format!("{} [y/N]", question)).as_str(),
+        "y" | "yes"
+    )
+}
+
+// The index of the chosen option, or None to skip
+pub fn choose(question: &str, options: &[String]) -> Option<usize> {
+    if options.len() == 1 {
+        return confirm(&format!("{} {}?", question, options[0])).then_some(0);
+    }
+
+    println!("{}:", question);
+    options
+        .iter()
+        .enumerate()
+        .for_each(|(index, option)| println!("  {}) {}", index + 1, option));
+
+    read_answer(&format!(
+        "Choose 1-{}, or press Enter to skip:",
+        options.len()
+    ))
+    .parse::<usize>()
+    .ok()
+    .filter(|choice| (1..=options.len()).contains(choice))
+    .map(|choice| choice - 1)
+}
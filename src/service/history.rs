@@ -0,0 +1,343 @@
+// Journal of the changes made to bookmarks, used to undo them
+
+use super::{ensure_folder, get_bookmark_at, BookmarkServiceError};
+use crate::store::{BookmarkStore, StoreError};
+use crate::Bookmark;
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryAction {
+    Create,
+    Update,
+    // Moved to the trash
+    Delete,
+    // Restored from the trash
+    Restore,
+}
+
+impl HistoryAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HistoryAction::Create => "create",
+            HistoryAction::Update => "update",
+            HistoryAction::Delete => "delete",
+            HistoryAction::Restore => "restore",
+        }
+    }
+
+    pub fn parse(action: &str) -> Option<HistoryAction> {
+        match action {
+            "create" => Some(HistoryAction::Create),
+            "update" => Some(HistoryAction::Update),
+            "delete" => Some(HistoryAction::Delete),
+            "restore" => Some(HistoryAction::Restore),
+            _ => None,
+        }
+    }
+}
+
+// A change to a bookmark, with its state before and after the change
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct HistoryEntry {
+    pub id: Option<i32>,
+    pub bookmark_id: i32,
+    pub action: HistoryAction,
+    pub before: Option<Bookmark>,
+    pub after: Option<Bookmark>,
+    pub created_at: i64,
+    pub undone: bool,
+
+    // The command line that made the change, and the user who ran it
+    pub command: Option<String>,
+    pub user: Option<String>,
+
+    // The entry reverted by this change, when it was made by undo
+    pub undo_of: Option<i32>,
+
+    // The first entry of the changes made together with this one, which are undone together
+    pub batch: Option<i32>,
+}
+
+impl HistoryEntry {
+    pub fn new(
+        action: HistoryAction,
+        before: Option<Bookmark>,
+        after: Option<Bookmark>,
+    ) -> HistoryEntry {
+        let bookmark_id = after
+            .as_ref()
+            .or(before.as_ref())
+            .and_then(|bookmark| bookmark.id)
+            .unwrap_or_default();
+
+        HistoryEntry {
+            id: None,
+            bookmark_id,
+            action,
+            before,
+            after,
+            created_at: crate::time::now(),
+            undone: false,
+            command: Some(current_command()),
+            user: crate::paths::non_empty_env("USER").or(crate::paths::non_empty_env("LOGNAME")),
+            undo_of: None,
+            batch: None,
+        }
+    }
+
+    // A short description of the change, such as "deletion of notes (/home/me/notes)"
+    pub fn describe(&self) -> String {
+        let bookmark = self.after.as_ref().or(self.before.as_ref());
+        let name = bookmark
+            .and_then(|bookmark| bookmark.name.clone())
+            .unwrap_or_default();
+        let path = bookmark
+            .and_then(|bookmark| bookmark.path.clone())
+            .unwrap_or_default();
+
+        let change = match self.action {
+            HistoryAction::Create => "creation",
+            HistoryAction::Update => "update",
+            HistoryAction::Delete => "deletion",
+            HistoryAction::Restore => "restoration",
+        };
+        format!("{} of {} ({})", change, name, path)
+    }
+
+    // The fields changed between the states before and after the change
+    pub fn changes(&self) -> Vec<String> {
+        let (Some(before), Some(after)) = (&self.before, &self.after) else {
+            return vec![];
+        };
+
+        let fields = [
+            ("name", before.name.clone(), after.name.clone()),
+            ("path", before.path.clone(), after.path.clone()),
+            (
+                "description",
+                before.description.clone(),
+                after.description.clone(),
+            ),
+            ("folder", before.folder.clone(), after.folder.clone()),
+            ("position", before.position(), after.position()),
+            (
+                "working directory",
+                before.working_directory.clone(),
+                after.working_directory.clone(),
+            ),
+            ("pre hook", before.pre_hook.clone(), after.pre_hook.clone()),
+            (
+                "post hook",
+                before.post_hook.clone(),
+                after.post_hook.clone(),
+            ),
+            ("env", Some(before.env.join(" ")), Some(after.env.join(" "))),
+            (
+                "tags",
+                Some(before.tags.join(",")),
+                Some(after.tags.join(",")),
+            ),
+        ];
+        fields
+            .into_iter()
+            .filter(|(_, before, after)| before != after)
+            .map(|(field, before, after)| {
+                format!(
+                    "{}: {} -> {}",
+                    field,
+                    before
+                        .filter(|value| !value.is_empty())
+                        .unwrap_or("None".to_string()),
+                    after
+                        .filter(|value| !value.is_empty())
+                        .unwrap_or("None".to_string())
+                )
+            })
+            .collect()
+    }
+}
+
+impl std::fmt::Display for HistoryEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "#{} {} by {}: {}{}",
+            self.id.unwrap_or_default(),
+            crate::time::format(self.created_at),
+            self.user.clone().unwrap_or("unknown".to_string()),
+            self.describe(),
+            if self.undone { " (undone)" } else { "" }
+        )?;
+        if let Some(command) = &self.command {
+            write!(f, "\n    {}", command)?;
+        }
+        for change in self.changes() {
+            write!(f, "\n    {}", change)?;
+        }
+        Ok(())
+    }
+}
+
+// The command line of this process, with arguments containing spaces quoted
+fn current_command() -> String {
+    let mut args: Vec<String> = std::env::args()
+        .map(|arg| {
+            if arg.contains(char::is_whitespace) {
+                format!("'{}'", arg)
+            } else {
+                arg
+            }
+        })
+        .collect();
+
+    // Show the binary by name rather than by the path it was started from
+    if let Some(binary) = args.first_mut() {
+        if let Some(name) = std::path::Path::new(binary.as_str()).file_name() {
+            *binary = name.to_string_lossy().to_string();
+        }
+    }
+    args.join(" ")
+}
+
+// Record changes made to several bookmarks at once, so that undo reverts all of them
+pub fn add_history_batch(
+    repository: &dyn BookmarkStore,
+    entries: Vec<HistoryEntry>,
+) -> Result<(), StoreError> {
+    let mut batch = None;
+    for entry in entries {
+        let id = repository.add_history_entry(&HistoryEntry { batch, ..entry })?;
+        batch = batch.or(Some(id));
+    }
+    Ok(())
+}
+
+// Revert the most recent change that was not undone yet, along with the changes made
+// together with it, returning them. Changes to bookmarks removed from the trash since
+// can no longer be undone
+pub fn undo(repository: &dyn BookmarkStore) -> Result<Vec<HistoryEntry>, BookmarkServiceError> {
+    repository.transaction(|repository| {
+        let Some(last_entry) = repository.get_last_undoable_history_entry()? else {
+            return Ok(vec![]);
+        };
+        let entries: Vec<HistoryEntry> = match last_entry.batch {
+            Some(batch) => repository
+                .get_history(None)?
+                .into_iter()
+                .rev()
+                .filter(|entry| entry.id == Some(batch) || entry.batch == Some(batch))
+                .filter(|entry| !entry.undone)
+                .filter(|entry| repository.get_any_bookmark(entry.bookmark_id).is_ok())
+                .collect(),
+            None => vec![last_entry],
+        };
+
+        for entry in &entries {
+            undo_entry(repository, entry)?;
+        }
+        Ok(entries)
+    })
+}
+
+fn undo_entry(
+    repository: &dyn BookmarkStore,
+    entry: &HistoryEntry,
+) -> Result<(), BookmarkServiceError> {
+    let current = repository.get_any_bookmark(entry.bookmark_id)?;
+
+    let action = match entry.action {
+        // The bookmark did not exist before, and its state is kept in the journal
+        HistoryAction::Create => {
+            repository.delete_bookmark(entry.bookmark_id)?;
+            HistoryAction::Delete
+        }
+        HistoryAction::Update => {
+            let before = entry
+                .before
+                .clone()
+                .ok_or(BookmarkServiceError::InternalError)?;
+            // The folder may have been moved or renamed since
+            if let Some(folder) = &before.folder {
+                ensure_folder(repository, folder)?;
+            }
+            repository.update_bookmark(entry.bookmark_id, before.clone())?;
+            repository.set_tags(entry.bookmark_id, &before.tags)?;
+            HistoryAction::Update
+        }
+        HistoryAction::Delete => {
+            let path = current.path.clone().unwrap_or_default();
+            if get_bookmark_at(repository, &path, current.line, current.column)?.is_some() {
+                return Err(BookmarkServiceError::BookmarkAlreadyExists);
+            }
+            repository.restore_bookmark(entry.bookmark_id)?;
+            HistoryAction::Restore
+        }
+        HistoryAction::Restore => {
+            let deleted_at = entry
+                .before
+                .as_ref()
+                .and_then(|bookmark| bookmark.deleted_at)
+                .unwrap_or(crate::time::now());
+            repository.trash_bookmark(entry.bookmark_id, deleted_at)?;
+            HistoryAction::Delete
+        }
+    };
+
+    // Undoing is a change too, recorded but never undone itself
+    let after = match action {
+        HistoryAction::Delete => None,
+        _ => Some(repository.get_any_bookmark(entry.bookmark_id)?),
+    };
+    repository.add_history_entry(&HistoryEntry {
+        undo_of: entry.id,
+        ..HistoryEntry::new(action, Some(current), after)
+    })?;
+
+    repository.set_history_entry_undone(entry.id.unwrap())?;
+    Ok(())
+}
+
+// Bring a bookmark back to its state right after the given change
+pub fn revert(
+    repository: &dyn BookmarkStore,
+    entry_id: i32,
+) -> Result<Bookmark, BookmarkServiceError> {
+    repository.transaction(|repository| {
+        let entry = match repository.get_history_entry(entry_id) {
+            Err(StoreError::NotFound) => {
+                return Err(BookmarkServiceError::HistoryEntryNotFound(entry_id))
+            }
+            result => result?,
+        };
+        // Deletions leave nothing to revert to, deleted bookmarks are restored from the trash
+        let mut target = entry
+            .after
+            .clone()
+            .ok_or(BookmarkServiceError::CannotRevert(entry_id))?;
+        let current = repository
+            .get_bookmark(entry.bookmark_id)
+            .map_err(|_| BookmarkServiceError::IdNotFound(entry.bookmark_id))?;
+
+        let path = target.path.clone().unwrap_or_default();
+        let existing = get_bookmark_at(repository, &path, target.line, target.column)?;
+        if existing.is_some_and(|existing| existing.id != current.id) {
+            return Err(BookmarkServiceError::BookmarkAlreadyExists);
+        }
+
+        target.updated_at = Some(crate::time::now());
+        target.last_seen_existing = current.last_seen_existing;
+        if let Some(folder) = &target.folder {
+            ensure_folder(repository, folder)?;
+        }
+        repository.update_bookmark(entry.bookmark_id, target.clone())?;
+        repository.set_tags(entry.bookmark_id, &target.tags)?;
+
+        let after = repository.get_bookmark(entry.bookmark_id)?;
+        repository.add_history_entry(&HistoryEntry::new(
+            HistoryAction::Update,
+            Some(current),
+            Some(after.clone()),
+        ))?;
+        Ok(after)
+    })
+}
@@ -0,0 +1,1794 @@
+// What oxmark does with the bookmarks, for the command line and the TUI, through
+// the `BookmarkStore` trait whatever the backend
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use super::{Bookmark, BookmarkFilter, BookmarkKind, SortOrder};
+use crate::fuzzy_filter;
+use crate::import::{
+    ExportFormat, ImportEntry, ImportMode, ImportOptions, ImportSummary, NameConflict,
+};
+use crate::repair::{self, FileId};
+use crate::store::{BookmarkStore, StoreError};
+
+mod history;
+
+pub use history::{undo, HistoryAction, HistoryEntry};
+
+// Maximum number of results printed by a full-text search
+const SEARCH_LIMIT: usize = 50;
+
+/*
+ *
+ * Bookmark Service
+ *
+ */
+
+pub struct BookmarkService {
+    store: Box<dyn BookmarkStore>,
+}
+
+impl BookmarkService {
+    pub fn new(store: Box<dyn BookmarkStore>) -> BookmarkService {
+        BookmarkService { store }
+    }
+
+    // Hand the store over, such as to the interactive list
+    pub fn into_store(self) -> Box<dyn BookmarkStore> {
+        self.store
+    }
+
+    pub fn create(
+        &self,
+        path: String,
+        name: Option<String>,
+        description: Option<String>,
+        folder: Option<String>,
+        kind: Option<BookmarkKind>,
+        working_directory: Option<String>,
+    ) -> Result<(), BookmarkServiceError> {
+        // Only commands are never guessed from the input
+        let mut bookmark = match kind.unwrap_or(BookmarkKind::of_input(&path)) {
+            BookmarkKind::Command => {
+                let command = path.trim().to_string();
+                if command.is_empty() {
+                    return Err(BookmarkServiceError::EmptyCommand);
+                }
+                let working_directory = working_directory
+                    .map(|directory| get_working_directory(&directory))
+                    .transpose()?;
+                Bookmark::new_command(name, command, description, working_directory)
+            }
+            BookmarkKind::Url => Bookmark::new_url(name, path, description),
+            BookmarkKind::Remote => Bookmark::new_remote(name, path, description),
+            BookmarkKind::Path => {
+                let (path, line, column) = Bookmark::split_position(&path);
+                let mut bookmark = Bookmark::new(name, Some(path.clone()), description)
+                    .map_err(|_| BookmarkServiceError::TargetNotFound(path.clone()))?;
+                if line.is_some() && !Path::new(&path).is_file() {
+                    return Err(BookmarkServiceError::PositionOutsideFile(path));
+                }
+
+                bookmark.line = line;
+                bookmark.column = column;
+                bookmark
+            }
+        };
+        bookmark.folder = folder.map(|folder| self.get_folder(&folder)).transpose()?;
+
+        // Paths were just canonicalized, so they exist
+        let now = crate::time::now();
+        bookmark.created_at = Some(now);
+        bookmark.updated_at = Some(now);
+        if bookmark.kind == BookmarkKind::Path {
+            bookmark.last_seen_existing = Some(now);
+        }
+
+        // Check if a bookmark with the same path, line and column already exists
+        let path = bookmark.path.clone().unwrap();
+        if get_bookmark_at(&*self.store, &path, bookmark.line, bookmark.column)?.is_some() {
+            return Err(BookmarkServiceError::BookmarkAlreadyExists);
+        }
+        let file_id = match bookmark.kind {
+            BookmarkKind::Path => FileId::of(Path::new(bookmark.path.as_deref().unwrap())),
+            _ => None,
+        };
+        self.store.transaction(|repository| {
+            let id = repository.create_bookmark(bookmark)?;
+            if let Some(file_id) = file_id {
+                repository.set_file_id(id, file_id)?;
+            }
+            let bookmark = repository.get_bookmark(id)?;
+            repository.add_history_entry(&HistoryEntry::new(
+                HistoryAction::Create,
+                None,
+                Some(bookmark),
+            ))
+        })?;
+        Ok(())
+    }
+
+    // Deleted bookmarks are moved to the trash
+    pub fn delete(&self, path: String) -> Result<(), BookmarkServiceError> {
+        let bookmark = self.get_bookmark_by_input_path(path)?;
+        trash_bookmark(&*self.store, bookmark)?;
+        Ok(())
+    }
+
+    pub fn update(
+        &self,
+        id: i32,
+        mut path: Option<String>,
+        name: Option<String>,
+        description: Option<String>,
+    ) -> Result<(), BookmarkServiceError> {
+        //  Get the bookmark by id
+        let before = self
+            .store
+            .get_bookmark(id)
+            .map_err(|_| BookmarkServiceError::IdNotFound(id))?;
+
+        // URLs and remote targets are kept as given, and commands stay commands
+        let kind = path.as_deref().map(|path| match before.kind {
+            BookmarkKind::Command => BookmarkKind::Command,
+            _ => BookmarkKind::of_input(path),
+        });
+
+        // If the path is provided, canonicalize it along with its position in the file
+        let (mut line, mut column) = (None, None);
+        if let Some(input) = path.clone() {
+            if kind == Some(BookmarkKind::Path) {
+                let (file, file_line, file_column) = Bookmark::split_position(&input);
+                let abs_path = std::path::PathBuf::from(&file)
+                    .canonicalize()
+                    .map_err(|_| BookmarkServiceError::TargetNotFound(file.clone()))?;
+                if file_line.is_some() && !abs_path.is_file() {
+                    return Err(BookmarkServiceError::PositionOutsideFile(file));
+                }
+                path = Some(abs_path.to_string_lossy().to_string());
+                (line, column) = (file_line, file_column);
+            }
+
+            // If another bookmark already has this target, return an error
+            let existing = get_bookmark_at(&*self.store, path.as_deref().unwrap(), line, column)?;
+            if existing.is_some_and(|existing| existing.id != Some(id)) {
+                return Err(BookmarkServiceError::BookmarkAlreadyExists);
+            }
+        }
+
+        let mut bookmark = before.clone();
+        if path.is_some() {
+            bookmark.line = line;
+            bookmark.column = column;
+        }
+
+        // Update the bookmark
+        let now = crate::time::now();
+        if let Some(kind) = kind {
+            bookmark.kind = kind;
+            bookmark.last_seen_existing = (kind == BookmarkKind::Path).then_some(now);
+        }
+        let file_id = path
+            .as_deref()
+            .filter(|_| kind == Some(BookmarkKind::Path))
+            .and_then(|path| FileId::of(Path::new(path)));
+        bookmark.update(name, path, description);
+        bookmark.updated_at = Some(now);
+
+        self.store.transaction(|repository| {
+            repository.update_bookmark(id, bookmark.clone())?;
+            if let Some(file_id) = file_id {
+                repository.set_file_id(id, file_id)?;
+            }
+            repository.add_history_entry(&HistoryEntry::new(
+                HistoryAction::Update,
+                Some(before),
+                Some(bookmark),
+            ))
+        })?;
+
+        Ok(())
+    }
+
+    pub fn get_all(
+        &self,
+        pathsonly: bool,
+        filter: BookmarkFilter,
+        sort: SortOrder,
+    ) -> Result<(), BookmarkServiceError> {
+        let bookmarks = self.store.get_all_bookmarks().unwrap_or_else(|e| {
+            println!("{}", e);
+            std::process::exit(1);
+        });
+
+        let mut bookmarks: Vec<Bookmark> = bookmarks
+            .into_iter()
+            .filter(|bookmark| filter.matches(bookmark))
+            .collect();
+
+        sort.sort(&mut bookmarks);
+
+        // If pathsonly is true, print only the paths
+        if pathsonly {
+            bookmarks.iter().for_each(|bookmark| {
+                println!("{}", bookmark.path.clone().unwrap());
+            });
+
+            return Ok(());
+        }
+
+        // Otherwise, print all the bookmarks
+        bookmarks.iter().for_each(|bookmark| {
+            println!("{}", bookmark);
+        });
+
+        Ok(())
+    }
+
+    // The bookmarks matching the query, best match first
+    pub fn find(
+        &self,
+        query: &str,
+        fts: bool,
+        limit: Option<usize>,
+    ) -> Result<Vec<Bookmark>, BookmarkServiceError> {
+        let mut bookmarks = if fts {
+            self.store
+                .search_bookmarks(query, limit.unwrap_or(SEARCH_LIMIT))
+                .map_err(|_| BookmarkServiceError::InternalError)?
+                .into_iter()
+                .map(|(bookmark, _)| bookmark)
+                .collect()
+        } else {
+            let bookmarks = self
+                .store
+                .get_all_bookmarks()
+                .map_err(|_| BookmarkServiceError::InternalError)?;
+            fuzzy_filter::get_ranked_bookmarks(bookmarks, query.to_string())
+        };
+
+        if let Some(limit) = limit {
+            bookmarks.truncate(limit);
+        }
+        Ok(bookmarks)
+    }
+
+    pub fn print_bookmarks(&self, bookmarks: &[Bookmark], pathsonly: bool) {
+        bookmarks.iter().for_each(|bookmark| {
+            if pathsonly {
+                println!("{}", bookmark.path.clone().unwrap_or_default());
+            } else {
+                println!("{}", bookmark);
+            }
+        });
+    }
+
+    //
+    // Jump
+    //
+
+    // Find the bookmark meant by a name, trying the exact name before fuzzy matching
+    pub fn find_by_name(&self, name: &str) -> Result<NameMatch, BookmarkServiceError> {
+        let bookmarks = self.store.get_all_bookmarks()?;
+
+        let mut matches: Vec<Bookmark> = bookmarks
+            .iter()
+            .filter(|bookmark| bookmark.name.as_deref() == Some(name))
+            .cloned()
+            .collect();
+        if matches.is_empty() {
+            matches = fuzzy_filter::get_filtered_bookmarks(bookmarks, name.to_string());
+        }
+        match matches.len() {
+            0 => Err(BookmarkServiceError::NameNotFound(name.to_string())),
+            1 => Ok(NameMatch::Unique(Box::new(matches.remove(0)))),
+            _ => Ok(NameMatch::Ambiguous),
+        }
+    }
+
+    // Print the path of the bookmark, or the shell command to go to it, and count the visit
+    pub fn visit(
+        &self,
+        bookmark: Bookmark,
+        print_command: bool,
+    ) -> Result<(), BookmarkServiceError> {
+        let path = bookmark.path.clone().unwrap_or_default();
+
+        if print_command {
+            let command = crate::shell::get_command(&bookmark)
+                .ok_or(BookmarkServiceError::PathNotFound(path.clone()))?;
+            println!("{}", command);
+        } else {
+            println!("{}", path);
+        }
+
+        self.update_last_seen_existing(std::slice::from_ref(&bookmark));
+        self.store
+            .record_visit(bookmark.id.unwrap(), crate::time::now())?;
+        Ok(())
+    }
+
+    //
+    // Import
+    //
+
+    pub fn import(
+        &self,
+        entries: Vec<ImportEntry>,
+        options: ImportOptions,
+    ) -> Result<ImportSummary, BookmarkServiceError> {
+        let operations =
+            |repository: &dyn BookmarkStore| import_entries(repository, entries, options);
+
+        if options.dry_run {
+            self.store.rollback_transaction(operations)
+        } else {
+            self.store.transaction(operations)
+        }
+    }
+
+    pub fn export(
+        &self,
+        format: ExportFormat,
+        output: Option<PathBuf>,
+    ) -> Result<(), BookmarkServiceError> {
+        let mut bookmarks = self
+            .store
+            .get_all_bookmarks()
+            .map_err(|_| BookmarkServiceError::InternalError)?;
+        SortOrder::Id.sort(&mut bookmarks);
+
+        let contents =
+            crate::import::export(bookmarks, format).map_err(BookmarkServiceError::ExportError)?;
+
+        match output {
+            Some(output) => std::fs::write(&output, contents)
+                .map_err(|e| BookmarkServiceError::ExportError(e.to_string()))?,
+            None => print!("{}", contents),
+        }
+
+        Ok(())
+    }
+
+    // Rewrite the GTK bookmarks file with the bookmarks under the prefix, if any
+    pub fn export_gtk(
+        &self,
+        prefix: Option<String>,
+        output: Option<PathBuf>,
+    ) -> Result<(), BookmarkServiceError> {
+        let output = output.or(crate::import::gtk_bookmarks_file()).ok_or(
+            BookmarkServiceError::ExportError("could not find the GTK bookmarks file".to_string()),
+        )?;
+
+        let mut bookmarks = self
+            .store
+            .get_all_bookmarks()
+            .map_err(|_| BookmarkServiceError::InternalError)?;
+        SortOrder::Id.sort(&mut bookmarks);
+
+        // The sidebar only holds directories, each once
+        let mut exported_paths = HashSet::new();
+        bookmarks.retain(|bookmark| {
+            bookmark.kind == BookmarkKind::Path
+                && bookmark.path.as_ref().is_some_and(|path| {
+                    Path::new(path).is_dir() && exported_paths.insert(path.clone())
+                })
+        });
+        if let Some(prefix) = prefix {
+            let prefix = std::path::PathBuf::from(&prefix)
+                .canonicalize()
+                .unwrap_or(prefix.into());
+            bookmarks.retain(|bookmark| {
+                bookmark
+                    .path
+                    .as_ref()
+                    .is_some_and(|path| Path::new(path).starts_with(&prefix))
+            });
+        }
+
+        // The file may not exist yet
+        let contents = std::fs::read_to_string(&output).unwrap_or_default();
+        let result = output
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(&output, crate::import::write_gtk(&contents, &bookmarks)));
+        if let Err(e) = result {
+            return Err(BookmarkServiceError::ExportError(e.to_string()));
+        }
+
+        println!(
+            "Wrote {} bookmarks to {}",
+            bookmarks.len(),
+            output.display()
+        );
+        Ok(())
+    }
+
+    //
+    // Hooks
+    //
+
+    pub fn show_hooks(&self, path: String) -> Result<(), BookmarkServiceError> {
+        let bookmark = self.get_bookmark_by_input_path(path)?;
+        if bookmark.pre_hook.is_none() && bookmark.post_hook.is_none() && bookmark.env.is_empty() {
+            println!("No hooks");
+        }
+
+        if let Some(pre_hook) = &bookmark.pre_hook {
+            println!("pre: {}", pre_hook);
+        }
+        if let Some(post_hook) = &bookmark.post_hook {
+            println!("post: {}", post_hook);
+        }
+        bookmark
+            .env
+            .iter()
+            .for_each(|entry| println!("env: {}", entry));
+        Ok(())
+    }
+
+    // Empty commands remove the hook. Variables that are already set keep their place
+    pub fn set_hooks(
+        &self,
+        path: String,
+        pre_hook: Option<String>,
+        post_hook: Option<String>,
+        env: Vec<String>,
+    ) -> Result<(), BookmarkServiceError> {
+        let env = env
+            .into_iter()
+            .map(validate_env)
+            .collect::<Result<Vec<String>, BookmarkServiceError>>()?;
+
+        self.change_hooks(path, |bookmark| {
+            let non_empty = |hook: String| (!hook.trim().is_empty()).then_some(hook);
+            if let Some(pre_hook) = pre_hook {
+                bookmark.pre_hook = non_empty(pre_hook);
+            }
+            if let Some(post_hook) = post_hook {
+                bookmark.post_hook = non_empty(post_hook);
+            }
+
+            for entry in env {
+                let existing = bookmark
+                    .env
+                    .iter()
+                    .position(|existing| get_env_key(existing) == get_env_key(&entry));
+                match existing {
+                    Some(index) => bookmark.env[index] = entry,
+                    None => bookmark.env.push(entry),
+                }
+            }
+            Ok(())
+        })
+    }
+
+    pub fn unset_hooks(
+        &self,
+        path: String,
+        pre_hook: bool,
+        post_hook: bool,
+        keys: Vec<String>,
+    ) -> Result<(), BookmarkServiceError> {
+        self.change_hooks(path, |bookmark| {
+            if let Some(key) = keys
+                .iter()
+                .find(|key| !bookmark.env.iter().any(|entry| get_env_key(entry) == *key))
+            {
+                return Err(BookmarkServiceError::VariableNotFound(key.clone()));
+            }
+
+            if pre_hook {
+                bookmark.pre_hook = None;
+            }
+            if post_hook {
+                bookmark.post_hook = None;
+            }
+            bookmark
+                .env
+                .retain(|entry| !keys.iter().any(|key| get_env_key(entry) == key));
+            Ok(())
+        })
+    }
+
+    pub fn clear_hooks(&self, path: String) -> Result<(), BookmarkServiceError> {
+        self.change_hooks(path, |bookmark| {
+            bookmark.pre_hook = None;
+            bookmark.post_hook = None;
+            bookmark.env.clear();
+            Ok(())
+        })
+    }
+
+    fn change_hooks(
+        &self,
+        path: String,
+        change: impl FnOnce(&mut Bookmark) -> Result<(), BookmarkServiceError>,
+    ) -> Result<(), BookmarkServiceError> {
+        let before = self.get_bookmark_by_input_path(path)?;
+        let mut bookmark = before.clone();
+        change(&mut bookmark)?;
+        bookmark.updated_at = Some(crate::time::now());
+
+        self.store.transaction(|repository| {
+            repository.update_bookmark(bookmark.id.unwrap(), bookmark.clone())?;
+            repository.add_history_entry(&HistoryEntry::new(
+                HistoryAction::Update,
+                Some(before),
+                Some(bookmark),
+            ))
+        })?;
+        Ok(())
+    }
+
+    //
+    // Tags
+    //
+
+    pub fn add_tags(&self, path: String, tags: Vec<String>) -> Result<(), BookmarkServiceError> {
+        let bookmark = self.get_bookmark_by_input_path(path)?;
+        let tags = tags
+            .into_iter()
+            .map(validate_tag)
+            .collect::<Result<Vec<String>, BookmarkServiceError>>()?;
+
+        self.store.transaction(|repository| {
+            for tag in tags {
+                repository.add_tag(bookmark.id.unwrap(), &tag)?;
+            }
+            record_tags_update(repository, bookmark)
+        })
+    }
+
+    pub fn remove_tags(&self, path: String, tags: Vec<String>) -> Result<(), BookmarkServiceError> {
+        let bookmark = self.get_bookmark_by_input_path(path)?;
+        if let Some(tag) = tags.iter().find(|tag| !bookmark.tags.contains(tag)) {
+            return Err(BookmarkServiceError::TagNotFound(tag.clone()));
+        }
+
+        self.store.transaction(|repository| {
+            for tag in tags {
+                repository.remove_tag(bookmark.id.unwrap(), &tag)?;
+            }
+            record_tags_update(repository, bookmark)
+        })
+    }
+
+    pub fn list_tags(&self, path: Option<String>) -> Result<(), BookmarkServiceError> {
+        // With a path, list the tags of that bookmark only
+        if let Some(path) = path {
+            let bookmark = self.get_bookmark_by_input_path(path)?;
+            bookmark.tags.iter().for_each(|tag| println!("{}", tag));
+            return Ok(());
+        }
+
+        let tags = self
+            .store
+            .get_all_tags()
+            .map_err(|_| BookmarkServiceError::InternalError)?;
+
+        tags.iter().for_each(|(tag, count)| {
+            println!("{} ({})", tag, count);
+        });
+
+        Ok(())
+    }
+
+    pub fn rename_tag(
+        &self,
+        old_name: String,
+        new_name: String,
+    ) -> Result<(), BookmarkServiceError> {
+        let new_name = validate_tag(new_name)?;
+
+        // Renaming a tag to itself changes nothing, as long as the tag exists
+        if old_name == new_name {
+            let tags = self.store.get_all_tags()?;
+            return match tags.iter().any(|(name, _)| *name == old_name) {
+                true => Ok(()),
+                false => Err(BookmarkServiceError::TagNotFound(old_name)),
+            };
+        }
+
+        let result = self.store.transaction(|repository| {
+            let tagged_bookmarks: Vec<Bookmark> = repository
+                .get_all_bookmarks()?
+                .into_iter()
+                .filter(|bookmark| bookmark.tags.contains(&old_name))
+                .collect();
+
+            repository.rename_tag(&old_name, &new_name)?;
+            for bookmark in tagged_bookmarks {
+                record_tags_update(repository, bookmark)?;
+            }
+            Ok(())
+        });
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(BookmarkServiceError::DatabaseError(StoreError::NotFound)) => {
+                Err(BookmarkServiceError::TagNotFound(old_name))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    //
+    // Helpers
+    //
+
+    // Remember which bookmarks still point to an existing path
+    // Also remembers the file each bookmark points to, see `repair`
+    fn update_last_seen_existing(&self, bookmarks: &[Bookmark]) {
+        let existing_files: Vec<(i32, FileId)> = bookmarks
+            .iter()
+            .filter(|bookmark| bookmark.kind == BookmarkKind::Path)
+            .filter_map(|bookmark| {
+                let file_id = FileId::of(Path::new(&bookmark.path.clone().unwrap_or_default()))?;
+                Some((bookmark.id?, file_id))
+            })
+            .collect();
+
+        if let Err(e) = self
+            .store
+            .update_last_seen_existing(&existing_files, crate::time::now())
+        {
+            eprintln!("{}", e);
+        }
+    }
+
+    //
+    // Folders
+    //
+
+    // Missing parent folders are created too
+    pub fn create_folder(&self, path: String) -> Result<(), BookmarkServiceError> {
+        let path = normalize_folder(&path)?;
+        if self.store.get_folder_id(&path).is_ok() {
+            return Err(BookmarkServiceError::FolderAlreadyExists(path));
+        }
+
+        self.store
+            .transaction(|repository| ensure_folder(repository, &path))?;
+
+        println!("Created folder {}", path);
+        Ok(())
+    }
+
+    // Print the folders as a tree, with the number of bookmarks in each
+    pub fn list_folders(&self) -> Result<(), BookmarkServiceError> {
+        let mut folders = self.store.get_folders()?;
+        if folders.is_empty() {
+            println!("No folders");
+        }
+
+        folders.sort_by(|(a, _), (b, _)| a.split('/').cmp(b.split('/')));
+        folders.iter().for_each(|(path, count)| {
+            let depth = path.matches('/').count();
+            let name = path.rsplit('/').next().unwrap_or_default();
+            println!("{}{} ({})", "  ".repeat(depth), name, count);
+        });
+        Ok(())
+    }
+
+    // Move a bookmark into a folder, or out of any folder without one
+    pub fn move_bookmark(
+        &self,
+        path: String,
+        folder: Option<String>,
+    ) -> Result<(), BookmarkServiceError> {
+        let before = self.get_bookmark_by_input_path(path)?;
+        let folder = folder.map(|folder| self.get_folder(&folder)).transpose()?;
+
+        let mut bookmark = before.clone();
+        bookmark.folder = folder;
+        bookmark.updated_at = Some(crate::time::now());
+
+        self.store.transaction(|repository| {
+            repository.update_bookmark(bookmark.id.unwrap(), bookmark.clone())?;
+            repository.add_history_entry(&HistoryEntry::new(
+                HistoryAction::Update,
+                Some(before),
+                Some(bookmark),
+            ))
+        })?;
+        Ok(())
+    }
+
+    // Move a folder with its content into another folder, or to the top level without one
+    pub fn move_folder(
+        &self,
+        source: String,
+        destination: Option<String>,
+    ) -> Result<(), BookmarkServiceError> {
+        let source = self.get_folder(&source)?;
+        let destination = destination
+            .map(|destination| self.get_folder(&destination))
+            .transpose()?;
+
+        if let Some(destination) = &destination {
+            if *destination == source || destination.starts_with(&format!("{}/", source)) {
+                return Err(BookmarkServiceError::FolderIntoItself(source));
+            }
+        }
+
+        let name = source.rsplit('/').next().unwrap_or_default();
+        let new_path = match &destination {
+            Some(destination) => format!("{}/{}", destination, name),
+            None => name.to_string(),
+        };
+        if self.store.get_folder_id(&new_path).is_ok() {
+            return Err(BookmarkServiceError::FolderAlreadyExists(new_path));
+        }
+
+        let source_id = self.store.get_folder_id(&source)?;
+        let destination_id = destination
+            .map(|destination| self.store.get_folder_id(&destination))
+            .transpose()?;
+
+        // Every bookmark inside changes folder, recorded in one batch so that undo
+        // brings them all back
+        let is_inside = |bookmark: &Bookmark| {
+            bookmark.folder.as_ref().is_some_and(|folder| {
+                *folder == source || folder.starts_with(&format!("{}/", source))
+            })
+        };
+        self.store
+            .transaction(|repository| -> Result<(), BookmarkServiceError> {
+                let moved: Vec<Bookmark> = repository
+                    .get_all_bookmarks()?
+                    .into_iter()
+                    .filter(is_inside)
+                    .collect();
+                repository.move_folder(source_id, destination_id)?;
+
+                let entries = moved
+                    .into_iter()
+                    .map(|before| {
+                        let after = repository.get_bookmark(before.id.unwrap())?;
+                        Ok(HistoryEntry::new(
+                            HistoryAction::Update,
+                            Some(before),
+                            Some(after),
+                        ))
+                    })
+                    .collect::<Result<Vec<HistoryEntry>, StoreError>>()?;
+                history::add_history_batch(repository, entries)?;
+                Ok(())
+            })?;
+
+        println!("Moved folder {} to {}", source, new_path);
+        Ok(())
+    }
+
+    // The normalized path of an existing folder
+    fn get_folder(&self, path: &str) -> Result<String, BookmarkServiceError> {
+        let path = normalize_folder(path)?;
+        match self.store.get_folder_id(&path) {
+            Ok(_) => Ok(path),
+            Err(StoreError::NotFound) => Err(BookmarkServiceError::FolderNotFound(path)),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    //
+    // Trash
+    //
+
+    pub fn list_trash(&self) -> Result<(), BookmarkServiceError> {
+        let bookmarks = self.store.get_trashed_bookmarks()?;
+        if bookmarks.is_empty() {
+            println!("The trash is empty");
+        }
+        bookmarks.iter().for_each(|bookmark| {
+            println!(
+                "deleted: {}, {}",
+                crate::time::format(bookmark.deleted_at.unwrap_or_default()),
+                bookmark
+            )
+        });
+        Ok(())
+    }
+
+    // Restore the most recently deleted bookmark with the given path
+    pub fn restore(&self, path: String) -> Result<(), BookmarkServiceError> {
+        let abs_path = std::path::PathBuf::from(&path)
+            .canonicalize()
+            .map(|abs_path| abs_path.to_string_lossy().to_string())
+            .unwrap_or(path.clone());
+
+        let bookmark = self
+            .store
+            .get_trashed_bookmarks()?
+            .into_iter()
+            .find(|bookmark| bookmark.path.as_ref() == Some(&abs_path))
+            .ok_or_else(|| BookmarkServiceError::PathNotFound(path.clone()))?;
+
+        if get_bookmark_at(&*self.store, &abs_path, bookmark.line, bookmark.column)?.is_some() {
+            return Err(BookmarkServiceError::BookmarkAlreadyExists);
+        }
+
+        self.store.transaction(|repository| {
+            repository.restore_bookmark(bookmark.id.unwrap())?;
+            let restored = repository.get_bookmark(bookmark.id.unwrap())?;
+            repository.add_history_entry(&HistoryEntry::new(
+                HistoryAction::Restore,
+                Some(bookmark),
+                Some(restored),
+            ))
+        })?;
+
+        println!("Restored {}", path);
+        Ok(())
+    }
+
+    pub fn empty_trash(&self) -> Result<(), BookmarkServiceError> {
+        let count = self.store.empty_trash()?;
+        println!("Permanently deleted {} bookmarks", count);
+        Ok(())
+    }
+
+    // Move every bookmark under the old prefix to the new one, all at once or not at all.
+    // Nothing is changed when a new path is already bookmarked
+    pub fn relocate(
+        &self,
+        old_prefix: String,
+        new_prefix: String,
+        dry_run: bool,
+    ) -> Result<(), BookmarkServiceError> {
+        // The old prefix is usually gone already, so it is not canonicalized
+        let old_prefix = std::path::absolute(&old_prefix).unwrap_or(PathBuf::from(&old_prefix));
+        let new_prefix = PathBuf::from(&new_prefix)
+            .canonicalize()
+            .or(std::path::absolute(&new_prefix))
+            .unwrap_or(PathBuf::from(&new_prefix));
+
+        let bookmarks = self.store.get_all_bookmarks()?;
+        let moves: Vec<(Bookmark, String)> = bookmarks
+            .iter()
+            .filter(|bookmark| bookmark.kind == BookmarkKind::Path)
+            .filter_map(|bookmark| {
+                let rest = Path::new(bookmark.path.as_deref()?)
+                    .strip_prefix(&old_prefix)
+                    .ok()?;
+                let new_path = match rest.as_os_str().is_empty() {
+                    true => new_prefix.clone(),
+                    false => new_prefix.join(rest),
+                };
+                Some((bookmark.clone(), new_path.to_string_lossy().to_string()))
+            })
+            .collect();
+
+        if moves.is_empty() {
+            println!("No bookmarks under {}", old_prefix.display());
+            return Ok(());
+        }
+
+        // Paths are only free once their own bookmark is relocated too
+        let collisions: Vec<(&String, &Bookmark)> = moves
+            .iter()
+            .filter_map(|(_, new_path)| {
+                let existing = bookmarks
+                    .iter()
+                    .find(|bookmark| bookmark.path.as_ref() == Some(new_path))?;
+                let is_moved = moves.iter().any(|(moved, _)| moved.id == existing.id);
+                (!is_moved).then_some((new_path, existing))
+            })
+            .collect();
+        if !collisions.is_empty() {
+            collisions.iter().for_each(|(new_path, existing)| {
+                println!(
+                    "{} is already bookmarked (id {})",
+                    new_path,
+                    existing.id.unwrap_or_default()
+                )
+            });
+            return Err(BookmarkServiceError::PathCollisions(collisions.len()));
+        }
+
+        moves.iter().for_each(|(bookmark, new_path)| {
+            println!(
+                "{} -> {}",
+                bookmark.path.clone().unwrap_or_default(),
+                new_path
+            )
+        });
+        if dry_run {
+            println!("Would relocate {} bookmarks", moves.len());
+            return Ok(());
+        }
+
+        // The bookmarks are relocated in one batch, undone all at once
+        let now = crate::time::now();
+        self.store
+            .transaction(|repository| -> Result<(), BookmarkServiceError> {
+                let mut entries = vec![];
+                for (before, new_path) in &moves {
+                    let id = before.id.unwrap();
+                    let mut bookmark = before.clone();
+                    bookmark.path = Some(new_path.clone());
+                    bookmark.updated_at = Some(now);
+
+                    let file_id = FileId::of(Path::new(new_path));
+                    if file_id.is_some() {
+                        bookmark.last_seen_existing = Some(now);
+                    }
+                    repository.update_bookmark(id, bookmark.clone())?;
+                    if let Some(file_id) = file_id {
+                        repository.set_file_id(id, file_id)?;
+                    }
+                    entries.push(HistoryEntry::new(
+                        HistoryAction::Update,
+                        Some(before.clone()),
+                        Some(bookmark),
+                    ));
+                }
+                history::add_history_batch(repository, entries)?;
+                Ok(())
+            })?;
+
+        println!("Relocated {} bookmarks", moves.len());
+        Ok(())
+    }
+
+    //
+    // Missing targets
+    //
+
+    // Move the bookmarks whose target no longer exists to the trash
+    pub fn prune(&self, dry_run: bool, interactive: bool) -> Result<(), BookmarkServiceError> {
+        let missing = self.get_missing_bookmarks(!dry_run)?;
+        if missing.is_empty() {
+            println!("No missing bookmarks");
+            return Ok(());
+        }
+
+        let mut count = 0;
+        for bookmark in missing {
+            let description = describe(&bookmark);
+            if dry_run {
+                println!("Would move {} to the trash", description);
+                continue;
+            }
+            if interactive && !repair::confirm(&format!("Move {} to the trash?", description)) {
+                continue;
+            }
+
+            trash_bookmark(&*self.store, bookmark)?;
+            println!("Moved {} to the trash", description);
+            count += 1;
+        }
+
+        if !dry_run {
+            println!("Moved {} bookmarks to the trash", count);
+        }
+        Ok(())
+    }
+
+    // Look for the new location of the bookmarks whose target was moved, and offer to update them
+    pub fn repair(&self) -> Result<(), BookmarkServiceError> {
+        let missing = self.get_missing_bookmarks(true)?;
+        if missing.is_empty() {
+            println!("No missing bookmarks");
+            return Ok(());
+        }
+
+        let mut count = 0;
+        for bookmark in missing {
+            let id = bookmark.id.unwrap();
+            let path = bookmark.path.clone().unwrap_or_default();
+            let file_id = self.store.get_file_id(id)?;
+
+            // Paths bookmarked already are left out, a bookmark cannot be moved onto them
+            let candidates: Vec<String> = repair::find_candidates(Path::new(&path), file_id)
+                .into_iter()
+                .map(|candidate| candidate.to_string_lossy().to_string())
+                .filter(|candidate| self.store.get_bookmark_by_path(candidate.clone()).is_err())
+                .collect();
+            if candidates.is_empty() {
+                println!("No new location found for {}", describe(&bookmark));
+                continue;
+            }
+
+            let question = format!("{} was not found, move it to", describe(&bookmark));
+            if let Some(choice) = repair::choose(&question, &candidates) {
+                self.update(id, Some(candidates[choice].clone()), None, None)?;
+                count += 1;
+            }
+        }
+
+        println!("Repaired {} bookmarks", count);
+        Ok(())
+    }
+
+    // Bookmarks whose target does not exist, also remembering the others as seen
+    // existing unless nothing should be written
+    fn get_missing_bookmarks(
+        &self,
+        record_existing: bool,
+    ) -> Result<Vec<Bookmark>, BookmarkServiceError> {
+        let bookmarks = self.store.get_all_bookmarks()?;
+        if record_existing {
+            self.update_last_seen_existing(&bookmarks);
+        }
+
+        Ok(bookmarks
+            .into_iter()
+            .filter(|bookmark| {
+                bookmark.kind == BookmarkKind::Path
+                    && bookmark
+                        .path
+                        .as_ref()
+                        .is_some_and(|path| !Path::new(path).exists())
+            })
+            .collect())
+    }
+
+    //
+    // History
+    //
+
+    pub fn history(&self, bookmark_id: Option<i32>) -> Result<(), BookmarkServiceError> {
+        let entries = self.store.get_history(bookmark_id)?;
+        if entries.is_empty() {
+            println!("No history");
+        }
+        entries.iter().for_each(|entry| println!("{}", entry));
+        Ok(())
+    }
+
+    pub fn revert(&self, entry_id: i32) -> Result<(), BookmarkServiceError> {
+        let bookmark = history::revert(&*self.store, entry_id)?;
+        println!("Reverted to {}", bookmark);
+        Ok(())
+    }
+
+    pub fn undo(&self) -> Result<(), BookmarkServiceError> {
+        let entries = history::undo(&*self.store)?;
+        if entries.is_empty() {
+            println!("Nothing to undo");
+        }
+        entries
+            .iter()
+            .for_each(|entry| println!("Undid the {}", entry.describe()));
+        Ok(())
+    }
+
+    // Get a bookmark from a path given on the command line, which may be relative
+    // and end with a line and column
+    fn get_bookmark_by_input_path(&self, path: String) -> Result<Bookmark, BookmarkServiceError> {
+        let (file, line, column) = Bookmark::split_position(&path);
+
+        // Paths that no longer exist cannot be canonicalized, so fall back to the raw input
+        let abs_path = std::path::PathBuf::from(&file)
+            .canonicalize()
+            .map(|abs_path| abs_path.to_string_lossy().to_string())
+            .unwrap_or(file);
+
+        if let Some(bookmark) = get_bookmark_at(&*self.store, &abs_path, line, column)? {
+            return Ok(bookmark);
+        }
+        // A file given without a position also matches its bookmarks at a position
+        match line {
+            Some(_) => Err(BookmarkServiceError::PathNotFound(path)),
+            None => self
+                .store
+                .get_bookmark_by_path(abs_path)
+                .map_err(|_| BookmarkServiceError::PathNotFound(path)),
+        }
+    }
+}
+
+pub enum NameMatch {
+    Unique(Box<Bookmark>),
+    // Several bookmarks match, the user has to pick one
+    Ambiguous,
+}
+
+// The bookmark of a path at a line and column. A file may be bookmarked once
+// as a whole and once at each position
+fn get_bookmark_at(
+    repository: &dyn BookmarkStore,
+    path: &str,
+    line: Option<u32>,
+    column: Option<u32>,
+) -> Result<Option<Bookmark>, StoreError> {
+    match repository.get_bookmark_at(path, line, column) {
+        Ok(bookmark) => Ok(Some(bookmark)),
+        Err(StoreError::NotFound) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+// Move a bookmark to the trash, recording it so that it can be undone
+pub fn trash_bookmark(
+    repository: &dyn BookmarkStore,
+    bookmark: Bookmark,
+) -> Result<(), StoreError> {
+    repository.transaction(|repository| {
+        repository.trash_bookmark(bookmark.id.unwrap(), crate::time::now())?;
+        repository.add_history_entry(&HistoryEntry::new(
+            HistoryAction::Delete,
+            Some(bookmark),
+            None,
+        ))?;
+        Ok(())
+    })
+}
+
+// Working directories are stored canonicalized, like paths
+fn get_working_directory(directory: &str) -> Result<String, BookmarkServiceError> {
+    std::path::PathBuf::from(directory)
+        .canonicalize()
+        .ok()
+        .filter(|directory| directory.is_dir())
+        .map(|directory| directory.to_string_lossy().to_string())
+        .ok_or(BookmarkServiceError::NotADirectory(directory.to_string()))
+}
+
+// A bookmark as "name (path)" in messages
+fn describe(bookmark: &Bookmark) -> String {
+    format!(
+        "{} ({})",
+        bookmark.name.clone().unwrap_or_default(),
+        bookmark.path.clone().unwrap_or_default()
+    )
+}
+
+// Folder paths are "/" separated names, extra slashes and spaces are dropped
+fn normalize_folder(path: &str) -> Result<String, BookmarkServiceError> {
+    let names: Vec<&str> = path
+        .split('/')
+        .map(|name| name.trim())
+        .filter(|name| !name.is_empty())
+        .collect();
+
+    if names.is_empty() || names.iter().any(|name| *name == "." || *name == "..") {
+        return Err(BookmarkServiceError::InvalidFolder(path.to_string()));
+    }
+    Ok(names.join("/"))
+}
+
+// The id of the folder with the given normalized path, created along with its parents if missing
+fn ensure_folder(repository: &dyn BookmarkStore, path: &str) -> Result<i32, BookmarkServiceError> {
+    let mut parent_id = None;
+    let mut current_path = String::new();
+
+    for name in path.split('/') {
+        if !current_path.is_empty() {
+            current_path.push('/');
+        }
+        current_path.push_str(name);
+
+        let id = match repository.get_folder_id(&current_path) {
+            Ok(id) => id,
+            Err(StoreError::NotFound) => repository.create_folder(name, parent_id)?,
+            Err(e) => return Err(e.into()),
+        };
+        parent_id = Some(id);
+    }
+
+    parent_id.ok_or(BookmarkServiceError::InvalidFolder(path.to_string()))
+}
+
+// Record a change to the tags of a bookmark, given its state before the change
+fn record_tags_update(
+    repository: &dyn BookmarkStore,
+    before: Bookmark,
+) -> Result<(), BookmarkServiceError> {
+    let after = repository.get_bookmark(before.id.unwrap())?;
+    repository.add_history_entry(&HistoryEntry::new(
+        HistoryAction::Update,
+        Some(before),
+        Some(after),
+    ))?;
+    Ok(())
+}
+
+// Add the entries to the database, recording what happened to each of them
+fn import_entries(
+    repository: &dyn BookmarkStore,
+    entries: Vec<ImportEntry>,
+    options: ImportOptions,
+) -> Result<ImportSummary, BookmarkServiceError> {
+    let now = crate::time::now();
+    let mut summary = ImportSummary::default();
+
+    // Replaced bookmarks go to the trash, so that they can still be restored
+    if options.mode == ImportMode::Replace {
+        for bookmark in repository.get_all_bookmarks()? {
+            summary
+                .replaced
+                .push(bookmark.path.clone().unwrap_or_default());
+            repository.trash_bookmark(bookmark.id.unwrap(), now)?;
+            repository.add_history_entry(&HistoryEntry::new(
+                HistoryAction::Delete,
+                Some(bookmark),
+                None,
+            ))?;
+        }
+    }
+
+    // The name of the bookmark of each path and position, looked up once rather than per entry
+    let mut bookmarked: HashMap<(String, Option<u32>, Option<u32>), String> = repository
+        .get_all_bookmarks()?
+        .into_iter()
+        .filter_map(|bookmark| {
+            let key = (bookmark.path?, bookmark.line, bookmark.column);
+            Some((key, bookmark.name.unwrap_or_default()))
+        })
+        .collect();
+
+    for entry in entries {
+        let below_threshold = options
+            .min_score
+            .is_some_and(|min_score| entry.score.unwrap_or(0.0) < min_score);
+        if below_threshold {
+            summary.below_threshold.push(entry.path());
+            continue;
+        }
+
+        // Only local paths are canonicalized and checked
+        let canonical_path = match entry.bookmark.kind {
+            BookmarkKind::Path => std::path::PathBuf::from(entry.path())
+                .canonicalize()
+                .map(|abs_path| abs_path.to_string_lossy().to_string()),
+            _ => Ok(entry.path()),
+        };
+        let (abs_path, is_missing) = match canonical_path {
+            Ok(abs_path) => (abs_path, false),
+            Err(_) if options.keep_missing => (entry.path(), true),
+            Err(_) => {
+                summary.missing.push(entry.path());
+                continue;
+            }
+        };
+
+        // This also catches paths listed twice in the imported file.
+        // Positions before the first line or column are dropped
+        let line = entry.bookmark.line.filter(|line| *line > 0);
+        let column = entry
+            .bookmark
+            .column
+            .filter(|column| *column > 0 && line.is_some());
+        if let Some(existing_name) = bookmarked.get(&(abs_path.clone(), line, column)) {
+            summary
+                .already_bookmarked
+                .push(format!("{} (bookmarked as {})", abs_path, existing_name));
+            continue;
+        }
+
+        let mut name = entry.bookmark.name.clone().unwrap_or(
+            std::path::Path::new(&abs_path)
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or(abs_path.clone()),
+        );
+
+        if let Ok(mut existing) = repository.get_bookmark_by_name(&name) {
+            match options.on_conflict {
+                NameConflict::Skip => {
+                    summary.name_taken.push(format!("{} ({})", name, abs_path));
+                    continue;
+                }
+                NameConflict::Rename => {
+                    let new_name = get_free_name(repository, &name)?;
+                    summary.renamed.push(format!("{} -> {}", name, new_name));
+                    name = new_name;
+                }
+                NameConflict::Overwrite => {
+                    let before = existing.clone();
+                    if let Some(path) = before.path.clone() {
+                        bookmarked.remove(&(path, before.line, before.column));
+                    }
+                    bookmarked.insert((abs_path.clone(), before.line, before.column), name.clone());
+                    existing.path = Some(abs_path.clone());
+                    existing.kind = entry.bookmark.kind;
+                    existing.updated_at = Some(now);
+                    existing.last_seen_existing = (!is_missing).then_some(now);
+                    repository.update_bookmark(existing.id.unwrap(), existing.clone())?;
+                    repository.add_history_entry(&HistoryEntry::new(
+                        HistoryAction::Update,
+                        Some(before),
+                        Some(existing),
+                    ))?;
+                    summary.overwritten.push(format!("{} ({})", name, abs_path));
+                    continue;
+                }
+            }
+        } else if is_missing {
+            summary.imported_missing.push(abs_path.clone());
+        } else {
+            summary.imported.push(abs_path.clone());
+        }
+
+        // Exported bookmarks keep their history. Entries from other tools only have
+        // a ranking, which is carried over so that the frecency order survives the move
+        let imported = entry.bookmark;
+        let visit_count = match entry.score {
+            Some(score) => score.round().max(1.0) as i32,
+            None => imported.visit_count,
+        };
+        let bookmark = Bookmark {
+            // Ids are only kept when replacing, and renumbered if a bookmark in the trash has them
+            id: imported.id.filter(|id| {
+                options.mode == ImportMode::Replace && repository.get_any_bookmark(*id).is_err()
+            }),
+            name: Some(name),
+            path: Some(abs_path),
+            line,
+            column,
+            visit_count,
+            created_at: imported.created_at.or(Some(now)),
+            updated_at: imported.updated_at.or(Some(now)),
+            last_seen_existing: if is_missing {
+                imported.last_seen_existing
+            } else {
+                Some(now)
+            },
+            ..imported
+        };
+        // Exported folders are recreated
+        let folder = bookmark
+            .folder
+            .as_ref()
+            .map(|folder| normalize_folder(folder))
+            .transpose()?;
+        if let Some(folder) = &folder {
+            ensure_folder(repository, folder)?;
+        }
+        let env = bookmark
+            .env
+            .iter()
+            .cloned()
+            .map(validate_env)
+            .collect::<Result<Vec<String>, BookmarkServiceError>>()?;
+        let mut bookmark = Bookmark {
+            folder,
+            env,
+            ..bookmark
+        };
+
+        // Hooks are shell code, only imported when asked for and always reported
+        let hooks: Vec<String> = [
+            ("pre hook", bookmark.pre_hook.clone()),
+            ("post hook", bookmark.post_hook.clone()),
+        ]
+        .into_iter()
+        .filter_map(|(kind, hook)| Some(format!("{}: {}", kind, hook?)))
+        .chain(bookmark.env.iter().map(|entry| format!("env: {}", entry)))
+        .map(|hook| format!("{}: {}", describe(&bookmark), hook))
+        .collect();
+        if options.with_hooks {
+            summary.hooks.extend(hooks);
+        } else if !hooks.is_empty() {
+            summary.hooks_left_out.push(describe(&bookmark));
+            bookmark.pre_hook = None;
+            bookmark.post_hook = None;
+            bookmark.env = vec![];
+        }
+
+        let key = (
+            bookmark.path.clone().unwrap_or_default(),
+            bookmark.line,
+            bookmark.column,
+        );
+        bookmarked.insert(key, bookmark.name.clone().unwrap_or_default());
+
+        let tags = bookmark.tags.clone();
+        let id = repository.create_bookmark(bookmark)?;
+        for tag in tags {
+            repository.add_tag(id, &validate_tag(tag)?)?;
+        }
+        repository.add_history_entry(&HistoryEntry::new(
+            HistoryAction::Create,
+            None,
+            Some(repository.get_bookmark(id)?),
+        ))?;
+    }
+
+    Ok(summary)
+}
+
+// The name with the lowest numbered suffix that no bookmark uses yet
+fn get_free_name(repository: &dyn BookmarkStore, name: &str) -> Result<String, StoreError> {
+    for suffix in 2.. {
+        let candidate = format!("{}-{}", name, suffix);
+        match repository.get_bookmark_by_name(&candidate) {
+            Err(StoreError::NotFound) => return Ok(candidate),
+            Err(e) => return Err(e),
+            Ok(_) => {}
+        }
+    }
+    unreachable!()
+}
+
+// Variables are "KEY=value", with a key the shell accepts as a name
+fn validate_env(entry: String) -> Result<String, BookmarkServiceError> {
+    let is_valid = entry.split_once('=').is_some_and(|(key, value)| {
+        let mut characters = key.chars();
+        characters
+            .next()
+            .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+            && characters.all(|character| character.is_ascii_alphanumeric() || character == '_')
+            && !value.contains('\n')
+    });
+    match is_valid {
+        true => Ok(entry),
+        false => Err(BookmarkServiceError::InvalidVariable(entry)),
+    }
+}
+
+fn get_env_key(entry: &str) -> &str {
+    entry.split_once('=').map(|(key, _)| key).unwrap_or(entry)
+}
+
+// Tags are stored comma separated in queries, and are matched as single words in the TUI search
+fn validate_tag(tag: String) -> Result<String, BookmarkServiceError> {
+    let tag = tag.trim().to_string();
+    if tag.is_empty() || tag.contains(',') || tag.contains(char::is_whitespace) {
+        return Err(BookmarkServiceError::InvalidTag(tag));
+    }
+    Ok(tag)
+}
+
+//
+// BookmarkServiceError
+//
+
+#[derive(Debug)]
+pub enum BookmarkServiceError {
+    IdNotFound(i32),
+    PathNotFound(String),
+    BookmarkAlreadyExists,
+    TagNotFound(String),
+    InvalidTag(String),
+    HistoryEntryNotFound(i32),
+    CannotRevert(i32),
+    NameNotFound(String),
+    FolderNotFound(String),
+    FolderAlreadyExists(String),
+    FolderIntoItself(String),
+    InvalidFolder(String),
+    ExportError(String),
+    // The number of new paths that are already bookmarked
+    PathCollisions(usize),
+    PositionOutsideFile(String),
+    TargetNotFound(String),
+    InvalidVariable(String),
+    VariableNotFound(String),
+    EmptyCommand,
+    NotADirectory(String),
+    DatabaseError(StoreError),
+    InternalError,
+}
+
+impl BookmarkServiceError {
+    pub fn message(&self) -> String {
+        match self {
+            BookmarkServiceError::PathNotFound(path) => {
+                format!("Bookmark with path {} not found", path)
+            }
+            BookmarkServiceError::IdNotFound(id) => format!("Bookmark with id {} not found", id),
+            BookmarkServiceError::BookmarkAlreadyExists => "Bookmark already exists".to_string(),
+            BookmarkServiceError::TagNotFound(tag) => format!("Tag {} not found", tag),
+            BookmarkServiceError::InvalidTag(tag) => {
+                format!(
+                    "Invalid tag '{}': tags cannot be empty or contain commas or spaces",
+                    tag
+                )
+            }
+            BookmarkServiceError::NameNotFound(name) => {
+                format!("No bookmark matches {}", name)
+            }
+            BookmarkServiceError::FolderNotFound(path) => format!(
+                "Folder {} not found, create it with 'oxmark folder create {}'",
+                path, path
+            ),
+            BookmarkServiceError::FolderAlreadyExists(path) => {
+                format!("Folder {} already exists", path)
+            }
+            BookmarkServiceError::FolderIntoItself(path) => {
+                format!("Folder {} cannot be moved into itself", path)
+            }
+            BookmarkServiceError::InvalidFolder(path) => format!(
+                "Invalid folder '{}': folder names cannot be empty, '.' or '..'",
+                path
+            ),
+            BookmarkServiceError::HistoryEntryNotFound(id) => {
+                format!("History entry {} not found", id)
+            }
+            BookmarkServiceError::CannotRevert(id) => format!(
+                "History entry {} is a deletion, restore the bookmark from the trash instead",
+                id
+            ),
+            BookmarkServiceError::ExportError(message) => {
+                format!("Could not export the bookmarks: {}", message)
+            }
+            BookmarkServiceError::InvalidVariable(entry) => {
+                format!(
+                    "Invalid variable '{}': expected KEY=value, with a key made of letters, digits and underscores",
+                    entry
+                )
+            }
+            BookmarkServiceError::VariableNotFound(key) => format!("Variable {} not found", key),
+            BookmarkServiceError::EmptyCommand => "The command cannot be empty".to_string(),
+            BookmarkServiceError::NotADirectory(path) => format!("{} is not a directory", path),
+            BookmarkServiceError::PositionOutsideFile(path) => {
+                format!(
+                    "{} is not a file, only files can have a line and column",
+                    path
+                )
+            }
+            BookmarkServiceError::TargetNotFound(path) => format!("{} does not exist", path),
+            BookmarkServiceError::PathCollisions(count) => format!(
+                "{} new paths are already bookmarked, nothing was relocated",
+                count
+            ),
+            BookmarkServiceError::DatabaseError(e) => e.message(),
+            BookmarkServiceError::InternalError => "Internal error".to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for BookmarkServiceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "oxmark - BookmarkServiceError: {}", self.message())
+    }
+}
+impl std::error::Error for BookmarkServiceError {}
+
+impl From<StoreError> for BookmarkServiceError {
+    fn from(e: StoreError) -> Self {
+        BookmarkServiceError::DatabaseError(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::MemoryStore;
+
+    fn temporary_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("oxmark-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.canonicalize().unwrap()
+    }
+
+    fn create_dirs(dir: &Path, names: &[&str]) -> Vec<String> {
+        names
+            .iter()
+            .map(|name| {
+                let path = dir.join(name);
+                std::fs::create_dir_all(&path).unwrap();
+                path.to_string_lossy().to_string()
+            })
+            .collect()
+    }
+
+    fn service() -> BookmarkService {
+        BookmarkService::new(Box::new(MemoryStore::new()))
+    }
+
+    fn add(service: &BookmarkService, path: &str, name: &str) -> Bookmark {
+        service
+            .create(
+                path.to_string(),
+                Some(name.to_string()),
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        service.store.get_bookmark_by_name(name).unwrap()
+    }
+
+    fn paths(service: &BookmarkService) -> Vec<String> {
+        let mut paths: Vec<String> = service
+            .store
+            .get_all_bookmarks()
+            .unwrap()
+            .into_iter()
+            .filter_map(|bookmark| bookmark.path)
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    fn import_options(mode: ImportMode) -> ImportOptions {
+        ImportOptions {
+            min_score: None,
+            on_conflict: NameConflict::Rename,
+            mode,
+            keep_missing: false,
+            dry_run: false,
+            with_hooks: false,
+        }
+    }
+
+    #[test]
+    fn create_update_and_undo() {
+        let dir = temporary_dir("service-undo");
+        let [old, new] = &create_dirs(&dir, &["old", "new"])[..] else {
+            unreachable!()
+        };
+        let service = service();
+        let bookmark = add(&service, old, "project");
+
+        service
+            .update(bookmark.id.unwrap(), Some(new.clone()), None, None)
+            .unwrap();
+        assert_eq!(paths(&service), [new.as_str()]);
+        assert!(matches!(
+            service.create(new.clone(), None, None, None, None, None),
+            Err(BookmarkServiceError::BookmarkAlreadyExists)
+        ));
+
+        service.undo().unwrap();
+        assert_eq!(paths(&service), [old.as_str()]);
+        service.undo().unwrap();
+        assert!(paths(&service).is_empty());
+        assert!(history::undo(&*service.store).unwrap().is_empty());
+    }
+
+    #[test]
+    fn adding_a_missing_path_fails() {
+        let dir = temporary_dir("service-missing");
+        let missing = dir.join("missing").to_string_lossy().to_string();
+        let service = service();
+
+        assert!(matches!(
+            service.create(missing.clone(), None, None, None, None, None),
+            Err(BookmarkServiceError::TargetNotFound(path)) if path == missing
+        ));
+        assert!(matches!(
+            service.create(format!("{}:3", missing), None, None, None, None, None),
+            Err(BookmarkServiceError::TargetNotFound(path)) if path == missing
+        ));
+        assert!(paths(&service).is_empty());
+    }
+
+    #[test]
+    fn update_switches_between_urls_and_paths() {
+        let dir = temporary_dir("service-kind");
+        let [path] = &create_dirs(&dir, &["local"])[..] else {
+            unreachable!()
+        };
+        let service = service();
+        let id = add(&service, path, "target").id.unwrap();
+
+        let url = "https://example.com/x".to_string();
+        service.update(id, Some(url.clone()), None, None).unwrap();
+        let bookmark = service.store.get_bookmark(id).unwrap();
+        assert_eq!(bookmark.kind, BookmarkKind::Url);
+        assert_eq!(bookmark.path, Some(url));
+
+        service.update(id, Some(path.clone()), None, None).unwrap();
+        let bookmark = service.store.get_bookmark(id).unwrap();
+        assert_eq!(bookmark.kind, BookmarkKind::Path);
+        assert_eq!(bookmark.path.as_ref(), Some(path));
+    }
+
+    #[test]
+    fn positions_in_a_file_are_bookmarked_separately() {
+        let dir = temporary_dir("service-positions");
+        let file = dir.join("main.rs");
+        std::fs::write(&file, "fn main() {}\n").unwrap();
+        let file = file.to_string_lossy().to_string();
+        let service = service();
+
+        add(&service, &file, "whole");
+        add(&service, &format!("{}:3", file), "line");
+        add(&service, &format!("{}:3:5", file), "column");
+
+        assert!(matches!(
+            service.create(format!("{}:3", file), None, None, None, None, None),
+            Err(BookmarkServiceError::BookmarkAlreadyExists)
+        ));
+    }
+
+    #[test]
+    fn renamed_tags_merge_and_renaming_to_itself_changes_nothing() {
+        let dir = temporary_dir("service-tags");
+        let [path] = &create_dirs(&dir, &["tagged"])[..] else {
+            unreachable!()
+        };
+        let service = service();
+        add(&service, path, "tagged");
+        service
+            .add_tags(path.clone(), vec!["rust".to_string(), "work".to_string()])
+            .unwrap();
+
+        service
+            .rename_tag("rust".to_string(), "rust".to_string())
+            .unwrap();
+        let tags = service.store.get_bookmark_by_name("tagged").unwrap().tags;
+        assert_eq!(tags, ["rust", "work"]);
+
+        service
+            .rename_tag("rust".to_string(), "work".to_string())
+            .unwrap();
+        let tags = service.store.get_bookmark_by_name("tagged").unwrap().tags;
+        assert_eq!(tags, ["work"]);
+
+        assert!(matches!(
+            service.rename_tag("rust".to_string(), "rustlang".to_string()),
+            Err(BookmarkServiceError::TagNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn import_merges_with_the_existing_bookmarks() {
+        let dir = temporary_dir("service-merge");
+        let [kept, imported] = &create_dirs(&dir, &["kept", "imported"])[..] else {
+            unreachable!()
+        };
+        let service = service();
+        add(&service, kept, "kept");
+
+        let entries = vec![
+            ImportEntry::new(kept.clone(), None),
+            ImportEntry::new(imported.clone(), None),
+            ImportEntry::new(dir.join("missing").to_string_lossy().to_string(), None),
+        ];
+        let summary = service
+            .import(entries, import_options(ImportMode::Merge))
+            .unwrap();
+
+        assert_eq!(summary.imported, [imported.as_str()]);
+        assert_eq!(summary.already_bookmarked.len(), 1);
+        assert_eq!(summary.missing.len(), 1);
+        assert_eq!(paths(&service), [imported.as_str(), kept.as_str()]);
+    }
+
+    #[test]
+    fn import_replaces_into_the_trash() {
+        let dir = temporary_dir("service-replace");
+        let [replaced, imported] = &create_dirs(&dir, &["replaced", "imported"])[..] else {
+            unreachable!()
+        };
+        let service = service();
+        let existing = add(&service, replaced, "replaced");
+
+        // The exported id is taken by the bookmark moved to the trash
+        let mut entry = ImportEntry::new(imported.clone(), None);
+        entry.bookmark.id = existing.id;
+        let summary = service
+            .import(vec![entry], import_options(ImportMode::Replace))
+            .unwrap();
+
+        assert_eq!(summary.replaced, [replaced.as_str()]);
+        assert_eq!(paths(&service), [imported.as_str()]);
+        let trash = service.store.get_trashed_bookmarks().unwrap();
+        assert_eq!(trash.len(), 1);
+        assert_eq!(trash[0].id, existing.id);
+        let new_id = service.store.get_bookmark_by_name("imported").unwrap().id;
+        assert_ne!(new_id, existing.id);
+    }
+
+    #[test]
+    fn relocate_is_undone_as_a_whole() {
+        let dir = temporary_dir("service-relocate");
+        let old = create_dirs(&dir, &["old/api", "old/web"]);
+        let new = create_dirs(&dir, &["new/api", "new/web"]);
+        let service = service();
+        add(&service, &old[0], "api");
+        add(&service, &old[1], "web");
+
+        service
+            .relocate(
+                dir.join("old").to_string_lossy().to_string(),
+                dir.join("new").to_string_lossy().to_string(),
+                false,
+            )
+            .unwrap();
+        assert_eq!(paths(&service), new);
+
+        let undone = history::undo(&*service.store).unwrap();
+        assert_eq!(undone.len(), 2);
+        assert_eq!(paths(&service), old);
+    }
+}
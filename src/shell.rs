@@ -0,0 +1,196 @@
+// Shell commands printed for the `ox` shell function to evaluate
+
+use clap::CommandFactory;
+
+use crate::{Bookmark, BookmarkKind, Cli};
+
+// Used to open URLs when neither OXMARK_BROWSER nor BROWSER is set
+const DEFAULT_BROWSER: &str = "xdg-open";
+
+// Lines around the `ox` function in the shell configuration
+const INIT_START: &str = "# >>> oxmark >>>";
+const INIT_END: &str = "# <<< oxmark <<<";
+
+// Quote a value so that the shell reads it as a single word
+pub fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+// Group a stored command or hook, so that the shell runs it as a whole within `&&` chains even
+// when it holds `;`, `||` or several lines. The closing brace goes on its own line in
+// case the command ends with a comment
+fn group(command: &str) -> String {
+    format!("{{ {}\n}}", command)
+}
+
+// Quote a value only if the shell would not read it as a single word as it is
+fn quote_if_needed(value: &str) -> String {
+    let is_plain = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "/._-+@:,%=".contains(c));
+    match is_plain {
+        true => value.to_string(),
+        false => quote(value),
+    }
+}
+
+// The snippet evaluated by the shell function: the pre hook, the exported variables,
+// the jump itself and the post hook, each only running if the previous one succeeded
+pub fn get_command(bookmark: &Bookmark) -> Option<String> {
+    let mut commands = vec![];
+    commands.extend(bookmark.pre_hook.as_deref().map(group));
+    commands.extend(bookmark.env.iter().map(|entry| {
+        let (key, value) = entry.split_once('=').unwrap_or((entry, ""));
+        format!("export {}={}", key, quote(value))
+    }));
+    commands.push(get_open_command(bookmark)?);
+    commands.extend(bookmark.post_hook.as_deref().map(group));
+
+    Some(commands.join(" && "))
+}
+
+// `cd` into directories, open files in $EDITOR and URLs in the browser, run commands
+// and log into remote directories. Nothing for missing paths
+fn get_open_command(bookmark: &Bookmark) -> Option<String> {
+    let path = bookmark.path.clone().unwrap_or_default();
+    match bookmark.kind {
+        BookmarkKind::Url => return Some(format!("{} {}", get_browser(), quote(&path))),
+        // The subshell keeps the current directory unchanged
+        BookmarkKind::Command => {
+            return Some(match &bookmark.working_directory {
+                Some(directory) => format!("(cd {} && {})", quote(directory), group(&path)),
+                None => group(&path),
+            })
+        }
+        BookmarkKind::Remote => {
+            let (host, path) = bookmark.remote_target()?;
+            return Some(get_ssh_command(&host, &path));
+        }
+        BookmarkKind::Path => {}
+    }
+
+    let path_buf = std::path::Path::new(&path);
+
+    if path_buf.is_dir() {
+        return Some(format!("cd {}", quote(&path)));
+    }
+    if path_buf.is_file() {
+        // The editor is left unquoted, it may hold arguments such as "code -w"
+        let editor = std::env::var("EDITOR").unwrap_or("vi".to_string());
+        return Some(get_editor_command(&editor, &path, bookmark));
+    }
+    None
+}
+
+// Open the file at the bookmarked line and column, in the syntax the editor understands
+fn get_editor_command(editor: &str, path: &str, bookmark: &Bookmark) -> String {
+    let (Some(line), Some(position)) = (bookmark.line, bookmark.position()) else {
+        return format!("{} {}", editor, quote(path));
+    };
+
+    let program = editor
+        .split_whitespace()
+        .next()
+        .and_then(|program| std::path::Path::new(program).file_name())
+        .map(|program| program.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    match program.as_str() {
+        "code" | "code-insiders" | "codium" | "cursor" => format!(
+            "{} --goto {}",
+            editor,
+            quote(&format!("{}:{}", path, position))
+        ),
+        "hx" | "helix" => format!("{} {}", editor, quote(&format!("{}:{}", path, position))),
+        "nano" => format!("{} +{} {}", editor, position.replace(':', ","), quote(path)),
+        // vi, vim, emacs and most other editors only take the line
+        _ => format!("{} +{} {}", editor, line, quote(path)),
+    }
+}
+
+// Log into the host with a login shell started in the directory. The host is passed to
+// ssh as given, so that aliases from ~/.ssh/config apply
+fn get_ssh_command(host: &str, path: &str) -> String {
+    // "~" is left unquoted for the remote shell to expand it
+    let remote_path = match path.strip_prefix('~') {
+        Some("") => "~".to_string(),
+        Some(rest) if rest.starts_with('/') => format!("~/{}", quote_if_needed(&rest[1..])),
+        _ => quote_if_needed(path),
+    };
+    let remote_command = format!("cd {} && exec $SHELL -l", remote_path);
+    format!(
+        "ssh -t {} {}",
+        quote_if_needed(host),
+        quote(&remote_command)
+    )
+}
+
+// Left unquoted like the editor, so that it may hold arguments
+fn get_browser() -> String {
+    crate::paths::non_empty_env("OXMARK_BROWSER")
+        .or(crate::paths::non_empty_env("BROWSER"))
+        .unwrap_or(DEFAULT_BROWSER.to_string())
+}
+
+// The `ox` function added to the shell configuration. Subcommands are passed on
+// to oxmark, anything else is a bookmark name whose command is evaluated.
+// It sits between marker lines, so that `oxmark init` can replace it once it changes
+pub fn get_init_function() -> String {
+    // Building the command adds the generated help subcommand
+    let mut command = Cli::command();
+    command.build();
+    let subcommands = command
+        .get_subcommands()
+        .map(|subcommand| subcommand.get_name().to_string())
+        .collect::<Vec<String>>()
+        .join("|");
+
+    format!(
+        "{}\nfunction ox() {{\n    case \"$1\" in\n        {}|-*) oxmark \"$@\" ;;\n        *) local command; command=\"$(oxmark command \"$@\")\" && eval \"$command\" ;;\n    esac\n}}\n{}",
+        INIT_START, subcommands, INIT_END
+    )
+}
+
+// The shell configuration with the `ox` function added, replacing any earlier version of it
+pub fn add_init_function(contents: &str) -> String {
+    let lines: Vec<&str> = contents.lines().collect();
+    let function = get_init_function();
+
+    let Some((start, end)) = find_init_function(&lines) else {
+        let separator = match contents.is_empty() || contents.ends_with('\n') {
+            true => "",
+            false => "\n",
+        };
+        return format!("{}{}\n{}\n", contents, separator, function);
+    };
+
+    let mut new_lines = lines[..start].to_vec();
+    new_lines.push(&function);
+    new_lines.extend(&lines[end + 1..]);
+    format!("{}\n", new_lines.join("\n"))
+}
+
+// The first and last line of the `ox` function. Versions written before the markers
+// start with an "# Oxmark" comment and end with the closing brace of the function
+fn find_init_function(lines: &[&str]) -> Option<(usize, usize)> {
+    if let Some(start) = lines.iter().position(|line| line.trim() == INIT_START) {
+        let length = lines[start..]
+            .iter()
+            .position(|line| line.trim() == INIT_END)?;
+        return Some((start, start + length));
+    }
+
+    let start = lines.iter().enumerate().position(|(index, line)| {
+        line.trim() == "# Oxmark"
+            && lines
+                .get(index + 1)
+                .is_some_and(|next| next.starts_with("function ox()"))
+    })?;
+    // The first version fit on a single line
+    if lines[start + 1].trim_end().ends_with('}') {
+        return Some((start, start + 1));
+    }
+    let length = lines[start + 1..].iter().position(|line| *line == "}")?;
+    Some((start, start + 1 + length))
+}
@@ -0,0 +1,329 @@
+use rusqlite::Connection;
+
+use super::SqliteRepositoryError;
+
+// Ordered list of schema migrations.
+// The migration at index N upgrades the database to schema version N + 1.
+// Released migrations must never be edited or reordered, only appended to.
+const MIGRATIONS: &[&str] = &[
+    // 1: Initial bookmarks table
+    "CREATE TABLE IF NOT EXISTS bookmarks (
+        id INTEGER PRIMARY KEY,
+        name TEXT,
+        path TEXT,
+        description TEXT
+    );",
+    // 2: Tags and the bookmark/tag join table
+    "CREATE TABLE tags (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL UNIQUE
+    );
+    CREATE TABLE bookmark_tags (
+        bookmark_id INTEGER NOT NULL REFERENCES bookmarks (id) ON DELETE CASCADE,
+        tag_id INTEGER NOT NULL REFERENCES tags (id) ON DELETE CASCADE,
+        PRIMARY KEY (bookmark_id, tag_id)
+    );",
+    // 3: Visit tracking for frecency ranking
+    "ALTER TABLE bookmarks ADD COLUMN visit_count INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE bookmarks ADD COLUMN last_visited INTEGER;",
+    // 4: Creation, modification and existence check timestamps.
+    // Left empty for existing bookmarks, whose history is unknown
+    "ALTER TABLE bookmarks ADD COLUMN created_at INTEGER;
+    ALTER TABLE bookmarks ADD COLUMN updated_at INTEGER;
+    ALTER TABLE bookmarks ADD COLUMN last_seen_existing INTEGER;",
+    // 5: Full-text index over name, path and description, kept in sync by triggers
+    "CREATE VIRTUAL TABLE bookmarks_fts USING fts5 (
+        name, path, description,
+        content = 'bookmarks', content_rowid = 'id',
+        tokenize = 'unicode61 remove_diacritics 2',
+        prefix = '2 3'
+    );
+    CREATE TRIGGER bookmarks_fts_insert AFTER INSERT ON bookmarks BEGIN
+        INSERT INTO bookmarks_fts (rowid, name, path, description)
+            VALUES (new.id, new.name, new.path, new.description);
+    END;
+    CREATE TRIGGER bookmarks_fts_delete AFTER DELETE ON bookmarks BEGIN
+        INSERT INTO bookmarks_fts (bookmarks_fts, rowid, name, path, description)
+            VALUES ('delete', old.id, old.name, old.path, old.description);
+    END;
+    CREATE TRIGGER bookmarks_fts_update AFTER UPDATE OF name, path, description ON bookmarks BEGIN
+        INSERT INTO bookmarks_fts (bookmarks_fts, rowid, name, path, description)
+            VALUES ('delete', old.id, old.name, old.path, old.description);
+        INSERT INTO bookmarks_fts (rowid, name, path, description)
+            VALUES (new.id, new.name, new.path, new.description);
+    END;
+    INSERT INTO bookmarks_fts (bookmarks_fts) VALUES ('rebuild');",
+    // 6: Soft deletion, and the journal of changes used to undo them.
+    // The bookmark states are stored as JSON, and the journal outlives the bookmarks
+    "ALTER TABLE bookmarks ADD COLUMN deleted_at INTEGER;
+    CREATE TABLE history (
+        id INTEGER PRIMARY KEY,
+        bookmark_id INTEGER NOT NULL,
+        action TEXT NOT NULL,
+        before TEXT,
+        after TEXT,
+        created_at INTEGER NOT NULL,
+        undone INTEGER NOT NULL DEFAULT 0
+    );",
+    // 7: Who made each change and with which command, and the change undone by it, if any
+    "ALTER TABLE history ADD COLUMN command TEXT;
+    ALTER TABLE history ADD COLUMN user TEXT;
+    ALTER TABLE history ADD COLUMN undo_of INTEGER;",
+    // 8: Nested folders, and a view resolving each folder to its full "a/b/c" path
+    "CREATE TABLE folders (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL,
+        parent_id INTEGER REFERENCES folders (id) ON DELETE CASCADE,
+        UNIQUE (parent_id, name)
+    );
+    ALTER TABLE bookmarks ADD COLUMN folder_id INTEGER REFERENCES folders (id) ON DELETE SET NULL;
+    CREATE VIEW folder_paths AS
+        WITH RECURSIVE paths (id, path) AS (
+            SELECT id, name FROM folders WHERE parent_id IS NULL
+            UNION ALL
+            SELECT folders.id, paths.path || '/' || folders.name FROM folders
+                JOIN paths ON folders.parent_id = paths.id
+        )
+        SELECT id, path FROM paths;",
+    // 9: The inode and device each target was last seen with, to find it again once moved
+    "ALTER TABLE bookmarks ADD COLUMN inode INTEGER;
+    ALTER TABLE bookmarks ADD COLUMN device INTEGER;",
+    // 10: Line and column of file bookmarks
+    "ALTER TABLE bookmarks ADD COLUMN line INTEGER;
+    ALTER TABLE bookmarks ADD COLUMN column INTEGER;",
+    // 11: Kind of bookmark, local paths or URLs
+    "ALTER TABLE bookmarks ADD COLUMN kind TEXT NOT NULL DEFAULT 'path';",
+    // 12: Commands run around a jump, and variables exported, one "KEY=value" per line
+    "ALTER TABLE bookmarks ADD COLUMN pre_hook TEXT;
+    ALTER TABLE bookmarks ADD COLUMN post_hook TEXT;
+    ALTER TABLE bookmarks ADD COLUMN env TEXT;",
+    // 13: Directory command bookmarks are run from
+    "ALTER TABLE bookmarks ADD COLUMN working_directory TEXT;",
+    // 14: Changes made to several bookmarks at once, undone together
+    "ALTER TABLE history ADD COLUMN batch INTEGER;",
+    // 15: Lookup of the bookmark of a path at a line and column
+    "CREATE INDEX bookmarks_position ON bookmarks (path, line, column);",
+];
+
+// The schema version this binary expects
+pub const SCHEMA_VERSION: i32 = MIGRATIONS.len() as i32;
+
+/*
+ *
+ * Migration runner
+ *
+ */
+
+pub fn migrate(
+    conn: &mut Connection,
+    db_path: &std::path::Path,
+) -> Result<(), SqliteRepositoryError> {
+    let current_version = get_user_version(conn)?;
+
+    // Refuse to touch a database created by a newer version of oxmark
+    if current_version > SCHEMA_VERSION {
+        return Err(SqliteRepositoryError::DatabaseTooNew(
+            current_version,
+            SCHEMA_VERSION,
+        ));
+    }
+
+    if current_version == SCHEMA_VERSION {
+        return Ok(());
+    }
+
+    // Back up existing data before changing the schema
+    if has_tables(conn)? {
+        backup(conn, db_path, current_version)?;
+    }
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(current_version as usize) {
+        let version = index as i32 + 1;
+        apply_migration(conn, version, migration)
+            .map_err(|e| SqliteRepositoryError::MigrationError(version, e))?;
+    }
+
+    Ok(())
+}
+
+fn apply_migration(conn: &mut Connection, version: i32, sql: &str) -> rusqlite::Result<()> {
+    // Each step runs in its own transaction, so a failing migration leaves
+    // the database at the previous version
+    let tx = conn.transaction()?;
+    tx.execute_batch(sql)?;
+    tx.pragma_update(None, "user_version", version)?;
+    tx.commit()
+}
+
+fn get_user_version(conn: &Connection) -> Result<i32, SqliteRepositoryError> {
+    let version = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    Ok(version)
+}
+
+fn has_tables(conn: &Connection) -> Result<bool, SqliteRepositoryError> {
+    let count: i32 = conn.query_row(
+        "SELECT count(*) FROM sqlite_master WHERE type = 'table'",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+fn backup(
+    conn: &Connection,
+    db_path: &std::path::Path,
+    version: i32,
+) -> Result<(), SqliteRepositoryError> {
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+
+    let file_name = format!(
+        "{}.v{}-{}.bak",
+        db_path.file_name().unwrap_or_default().to_string_lossy(),
+        version,
+        timestamp
+    );
+    let backup_path = db_path.with_file_name(file_name);
+
+    conn.execute("VACUUM INTO ?", [backup_path.to_string_lossy().to_string()])
+        .map_err(SqliteRepositoryError::BackupError)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sqlite::SqliteRepository;
+    use crate::store::{BookmarkStore, StoreError};
+    use crate::BookmarkKind;
+
+    fn temporary_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("oxmark-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    // The only table of oxmark before migrations, at user_version 0
+    fn create_baseline_database(path: &std::path::Path) {
+        let conn = Connection::open(path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS bookmarks (
+                id INTEGER PRIMARY KEY,
+                name TEXT,
+                path TEXT,
+                description TEXT
+            );
+            INSERT INTO bookmarks (name, path, description)
+                VALUES ('api', '/srv/api', 'The API'), ('web', '/srv/web', NULL);",
+        )
+        .unwrap();
+    }
+
+    fn backups(dir: &std::path::Path) -> Vec<std::path::PathBuf> {
+        std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == "bak"))
+            .collect()
+    }
+
+    #[test]
+    fn baseline_database_is_migrated_keeping_its_rows() {
+        let dir = temporary_dir("migrate-baseline");
+        let path = dir.join("oxmark.db");
+        create_baseline_database(&path);
+
+        let repository = SqliteRepository::new(&path).unwrap();
+        let bookmarks = repository.get_all_bookmarks().unwrap();
+        let rows: Vec<_> = bookmarks
+            .iter()
+            .map(|bookmark| {
+                (
+                    bookmark.name.as_deref().unwrap(),
+                    bookmark.path.as_deref().unwrap(),
+                    bookmark.description.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                ("api", "/srv/api", Some("The API")),
+                ("web", "/srv/web", None)
+            ]
+        );
+        // The new columns get their defaults
+        assert!(bookmarks
+            .iter()
+            .all(|bookmark| bookmark.kind == BookmarkKind::Path
+                && bookmark.visit_count == 0
+                && bookmark.deleted_at.is_none()
+                && bookmark.line.is_none()));
+        drop(repository);
+
+        let conn = Connection::open(&path).unwrap();
+        assert_eq!(get_user_version(&conn).unwrap(), SCHEMA_VERSION);
+        let history_columns: i32 = conn
+            .query_row(
+                "SELECT count(*) FROM pragma_table_info('history') WHERE name = 'batch'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(history_columns, 1);
+    }
+
+    #[test]
+    fn the_database_is_backed_up_before_migrating() {
+        let dir = temporary_dir("migrate-backup");
+        let path = dir.join("oxmark.db");
+        create_baseline_database(&path);
+
+        SqliteRepository::new(&path).unwrap();
+
+        let backups = backups(&dir);
+        assert_eq!(backups.len(), 1);
+        let name = backups[0]
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .to_string();
+        assert!(name.starts_with("oxmark.db.v0-"), "{}", name);
+
+        let backup = Connection::open(&backups[0]).unwrap();
+        assert_eq!(get_user_version(&backup).unwrap(), 0);
+        let count: i32 = backup
+            .query_row("SELECT count(*) FROM bookmarks", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn new_databases_are_not_backed_up() {
+        let dir = temporary_dir("migrate-new");
+        SqliteRepository::new(&dir.join("oxmark.db")).unwrap();
+
+        assert!(backups(&dir).is_empty());
+    }
+
+    #[test]
+    fn newer_databases_are_refused() {
+        let dir = temporary_dir("migrate-newer");
+        let path = dir.join("oxmark.db");
+        let conn = Connection::open(&path).unwrap();
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION + 1)
+            .unwrap();
+        drop(conn);
+
+        assert!(matches!(
+            SqliteRepository::new(&path),
+            Err(StoreError::DatabaseError(
+                SqliteRepositoryError::DatabaseTooNew(..)
+            ))
+        ));
+    }
+}
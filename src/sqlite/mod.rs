@@ -1,342 +1,666 @@
+use std::path::Path;
+
 use rusqlite::Connection;
 
-use super::Bookmark;
+use super::{Bookmark, BookmarkKind};
+use crate::repair::FileId;
+use crate::service::{HistoryAction, HistoryEntry};
+use crate::store::{BookmarkStore, StoreError};
 
-const DATABASE_DIR: &str = ".local/share/oxmark";
-const DATABASE_NAME: &str = "oxmark.db";
+mod migrations;
 
 /*
  *
- * SQLite Service
+ * SQLite Repository
  *
  */
 
-pub struct SqliteService {
-    sqlite_repository: SqliteRepository,
+#[derive(Debug)]
+pub struct SqliteRepository {
+    conn: Connection,
 }
 
-impl SqliteService {
-    pub fn new() -> Result<SqliteService, SqliteServiceError> {
-        let result = SqliteRepository::new();
+impl SqliteRepository {
+    pub fn new(db_path: &Path) -> Result<SqliteRepository, StoreError> {
+        let mut result = Connection::open(db_path);
 
-        if let Err(_) = result {
-            return Err(SqliteServiceError::InternalError);
-        }
-        Ok(SqliteService {
-            sqlite_repository: result.unwrap(),
-        })
-    }
-    pub fn create(
-        &self,
-        path: String,
-        name: Option<String>,
-        description: Option<String>,
-    ) -> Result<(), SqliteServiceError> {
-        let bookmark = Bookmark::new(name, Some(path), description);
-
-        // Check if a bookmark with the same path already exists
-        let check_bookmark = self
-            .sqlite_repository
-            .get_bookmark_by_path(bookmark.path.clone().unwrap());
-        if let Ok(_) = check_bookmark {
-            return Err(SqliteServiceError::BookmarkAlreadyExists);
+        // If the connection failed, try to create the database file
+        if result.is_err() {
+            if let Some(db_dir_path) = db_path.parent() {
+                if let Err(e) = std::fs::create_dir_all(db_dir_path) {
+                    return Err(StoreError::IoError(e));
+                }
+            }
+            if !db_path.exists() {
+                let file_creation_result = std::fs::File::create(db_path);
+                if let Err(e) = file_creation_result {
+                    return Err(StoreError::IoError(e));
+                }
+
+                result = Connection::open(db_path);
+            }
         }
-        let result = self.sqlite_repository.create_bookmark(bookmark);
-        if let Err(_) = result {
-            return Err(SqliteServiceError::InternalError);
+
+        // If the connection failed, return an error
+        if result.is_err() {
+            return Err(SqliteRepositoryError::ConnectionError.into());
         }
-        Ok(())
-    }
-    pub fn delete(&self, path: String) -> Result<(), SqliteServiceError> {
-        // TODO: handle canonicalize errors
 
-        let abs_path = std::path::PathBuf::from(path.clone())
-            .canonicalize()
-            .unwrap()
-            .to_str()
-            .unwrap()
-            .to_string();
+        let mut conn = result.unwrap();
 
-        let bookmark_result = self.sqlite_repository.get_bookmark_by_path(abs_path);
+        // Needed for the ON DELETE CASCADE clauses of the join tables
+        conn.pragma_update(None, "foreign_keys", true)?;
 
-        if let Err(_) = bookmark_result {
-            return Err(SqliteServiceError::PathNotFound(path));
-        }
+        // Bring the schema up to date
+        migrations::migrate(&mut conn, db_path)?;
 
-        let bookmark = bookmark_result.unwrap();
+        Ok(SqliteRepository { conn })
+    }
 
-        let result = self.sqlite_repository.delete_bookmark(bookmark.id.unwrap());
+    fn delete_unused_tags(&self) -> Result<(), StoreError> {
+        self.conn.execute(
+            "DELETE FROM tags WHERE id NOT IN (SELECT tag_id FROM bookmark_tags)",
+            [],
+        )?;
+        Ok(())
+    }
 
-        if let Err(_) = result {
-            return Err(SqliteServiceError::InternalError);
+    // Bookmarks would silently lose a folder that does not exist
+    fn check_folder(&self, folder: &Option<String>) -> Result<(), StoreError> {
+        match folder.as_ref().map(|folder| self.get_folder_id(folder)) {
+            Some(Err(StoreError::NotFound)) => Err(StoreError::FolderNotFound(
+                folder.clone().unwrap_or_default(),
+            )),
+            Some(Err(e)) => Err(e),
+            _ => Ok(()),
         }
+    }
+}
 
+impl BookmarkStore for SqliteRepository {
+    // Transactions do not nest, see `BookmarkStore::transaction`
+    fn begin(&self) -> Result<(), StoreError> {
+        self.conn.execute_batch("BEGIN DEFERRED")?;
         Ok(())
     }
 
-    pub fn update(
-        &self,
-        id: i32,
-        mut path: Option<String>,
-        name: Option<String>,
-        description: Option<String>,
-    ) -> Result<(), SqliteServiceError> {
-        // If the path is provided, canonicalize it and get the bookmark by path
-        if path.is_some() {
-            // TODO: handle canonicalize errors
-
-            path = Option::from(
-                std::path::PathBuf::from(path.clone().unwrap())
-                    .canonicalize()
-                    .unwrap()
-                    .to_str()
-                    .unwrap()
-                    .to_string(),
-            );
-
-            let test_bookmark = self
-                .sqlite_repository
-                .get_bookmark_by_path(path.clone().unwrap());
-
-            // If the bookmark already exists, return an error
-            if let Ok(_) = test_bookmark {
-                return Err(SqliteServiceError::BookmarkAlreadyExists);
-            }
-        }
+    fn commit(&self) -> Result<(), StoreError> {
+        self.conn.execute_batch("COMMIT")?;
+        Ok(())
+    }
+
+    fn rollback(&self) -> Result<(), StoreError> {
+        self.conn.execute_batch("ROLLBACK")?;
+        Ok(())
+    }
 
-        //  Get the bookmark by id
-        let result = self.sqlite_repository.get_bookmark(id);
-        if let Err(_) = result {
-            return Err(SqliteServiceError::IdNotFound(id));
+    fn get_all_bookmarks(&self) -> Result<Vec<Bookmark>, StoreError> {
+        let mut bookmarks_vec: Vec<Bookmark> = Vec::new();
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM bookmarks WHERE deleted_at IS NULL",
+            BOOKMARK_COLUMNS
+        ))?;
+        let bookmarks = stmt.query_map([], bookmark_from_row)?;
+        for bookmark in bookmarks {
+            bookmarks_vec.push(bookmark?);
         }
+        Ok(bookmarks_vec)
+    }
+    fn get_bookmark(&self, id: i32) -> Result<Bookmark, StoreError> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM bookmarks WHERE id = ? AND deleted_at IS NULL",
+            BOOKMARK_COLUMNS
+        ))?;
+        let bookmark = stmt.query_row([&id], bookmark_from_row)?;
+        Ok(bookmark)
+    }
 
-        let mut bookmark = result.unwrap();
+    // Including the bookmarks in the trash
+    fn get_any_bookmark(&self, id: i32) -> Result<Bookmark, StoreError> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM bookmarks WHERE id = ?",
+            BOOKMARK_COLUMNS
+        ))?;
+        let bookmark = stmt.query_row([&id], bookmark_from_row)?;
+        Ok(bookmark)
+    }
 
-        // Update the bookmark
-        bookmark.update(name, path, description);
+    fn get_bookmark_by_path(&self, path: String) -> Result<Bookmark, StoreError> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM bookmarks WHERE path = ? AND deleted_at IS NULL",
+            BOOKMARK_COLUMNS
+        ))?;
+        let bookmark = stmt.query_row([&path], bookmark_from_row)?;
+        Ok(bookmark)
+    }
 
-        let result = self.sqlite_repository.update_bookmark(id, bookmark.clone());
+    fn get_bookmark_at(
+        &self,
+        path: &str,
+        line: Option<u32>,
+        column: Option<u32>,
+    ) -> Result<Bookmark, StoreError> {
+        // IS compares NULL positions too, and still uses the index
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM bookmarks
+                WHERE path = ? AND line IS ? AND column IS ? AND deleted_at IS NULL
+                ORDER BY id LIMIT 1",
+            BOOKMARK_COLUMNS
+        ))?;
+        let bookmark = stmt.query_row(rusqlite::params![path, line, column], bookmark_from_row)?;
+        Ok(bookmark)
+    }
+
+    fn get_bookmark_by_name(&self, name: &str) -> Result<Bookmark, StoreError> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM bookmarks WHERE name = ? AND deleted_at IS NULL ORDER BY id LIMIT 1",
+            BOOKMARK_COLUMNS
+        ))?;
+        let bookmark = stmt.query_row([name], bookmark_from_row)?;
+        Ok(bookmark)
+    }
 
-        if let Err(_) = result {
-            return Err(SqliteServiceError::InternalError);
+    // Returns the id of the new bookmark, which is picked by SQLite unless given
+    fn create_bookmark(&self, bookmark: Bookmark) -> Result<i32, StoreError> {
+        self.check_folder(&bookmark.folder)?;
+        let mut stmt = self.conn.prepare(
+            "INSERT INTO bookmarks (id, name, path, description, folder_id, visit_count,
+                last_visited, created_at, updated_at, last_seen_existing, line, column, kind,
+                pre_hook, post_hook, env, working_directory)
+                VALUES (?, ?, ?, ?, (SELECT id FROM folder_paths WHERE path = ?),
+                    ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )?;
+        stmt.execute(rusqlite::params![
+            bookmark.id,
+            bookmark.name,
+            bookmark.path,
+            bookmark.description,
+            bookmark.folder,
+            bookmark.visit_count,
+            bookmark.last_visited,
+            bookmark.created_at,
+            bookmark.updated_at,
+            bookmark.last_seen_existing,
+            bookmark.line,
+            bookmark.column,
+            bookmark.kind.as_str(),
+            bookmark.pre_hook,
+            bookmark.post_hook,
+            get_env_column(&bookmark.env),
+            bookmark.working_directory,
+        ])?;
+        Ok(self.conn.last_insert_rowid() as i32)
+    }
+
+    fn update_bookmark(&self, id: i32, bookmark: Bookmark) -> Result<(), StoreError> {
+        self.check_folder(&bookmark.folder)?;
+        let mut stmt = self.conn.prepare(
+            "UPDATE bookmarks SET name = ?, path = ?, description = ?,
+                folder_id = (SELECT id FROM folder_paths WHERE path = ?),
+                updated_at = ?, last_seen_existing = ?, line = ?, column = ?, kind = ?,
+                pre_hook = ?, post_hook = ?, env = ?, working_directory = ?
+                WHERE id = ?",
+        )?;
+        stmt.execute(rusqlite::params![
+            bookmark.name,
+            bookmark.path,
+            bookmark.description,
+            bookmark.folder,
+            bookmark.updated_at,
+            bookmark.last_seen_existing,
+            bookmark.line,
+            bookmark.column,
+            bookmark.kind.as_str(),
+            bookmark.pre_hook,
+            bookmark.post_hook,
+            get_env_column(&bookmark.env),
+            bookmark.working_directory,
+            id,
+        ])?;
+        Ok(())
+    }
+
+    fn update_last_seen_existing(
+        &self,
+        files: &[(i32, FileId)],
+        timestamp: i64,
+    ) -> Result<(), StoreError> {
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "UPDATE bookmarks SET last_seen_existing = ?, inode = ?, device = ? WHERE id = ?",
+            )?;
+            for (id, file_id) in files {
+                stmt.execute(rusqlite::params![
+                    timestamp,
+                    file_id.inode as i64,
+                    file_id.device as i64,
+                    id
+                ])?;
+            }
         }
+        tx.commit()?;
+        Ok(())
+    }
 
+    fn set_file_id(&self, id: i32, file_id: FileId) -> Result<(), StoreError> {
+        self.conn.execute(
+            "UPDATE bookmarks SET inode = ?, device = ? WHERE id = ?",
+            rusqlite::params![file_id.inode as i64, file_id.device as i64, id],
+        )?;
         Ok(())
     }
 
-    pub fn get_all(&self, pathsonly: bool) -> Result<(), SqliteServiceError> {
-        let bookmarks = self
-            .sqlite_repository
-            .get_all_bookmarks()
-            .unwrap_or_else(|e| {
-                println!("{}", e);
-                std::process::exit(1);
-            });
+    // The file the bookmark pointed to when it was last seen existing
+    fn get_file_id(&self, id: i32) -> Result<Option<FileId>, StoreError> {
+        let (inode, device): (Option<i64>, Option<i64>) = self.conn.query_row(
+            "SELECT inode, device FROM bookmarks WHERE id = ?",
+            [id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        Ok(inode.zip(device).map(|(inode, device)| FileId {
+            inode: inode as u64,
+            device: device as u64,
+        }))
+    }
 
-        // If pathsonly is true, print only the paths
-        if pathsonly {
-            bookmarks.iter().for_each(|bookmark| {
-                println!("{}", bookmark.path.clone().unwrap());
-            });
+    fn delete_bookmark(&self, id: i32) -> Result<(), StoreError> {
+        let mut stmt = self.conn.prepare("DELETE FROM bookmarks WHERE id = ?")?;
+        stmt.execute([&id])?;
+        self.delete_unused_tags()?;
+        Ok(())
+    }
 
-            return Ok(());
-        }
+    //
+    // Trash
+    //
 
-        // Otherwise, print all the bookmarks
-        bookmarks.iter().for_each(|bookmark| {
-            println!("{}", bookmark.to_string());
-        });
+    fn trash_bookmark(&self, id: i32, timestamp: i64) -> Result<(), StoreError> {
+        self.conn.execute(
+            "UPDATE bookmarks SET deleted_at = ? WHERE id = ?",
+            rusqlite::params![timestamp, id],
+        )?;
+        Ok(())
+    }
 
+    fn restore_bookmark(&self, id: i32) -> Result<(), StoreError> {
+        self.conn
+            .execute("UPDATE bookmarks SET deleted_at = NULL WHERE id = ?", [id])?;
         Ok(())
     }
-}
 
-//
-// SqliteServiceError
-//
+    // Most recently deleted first
+    fn get_trashed_bookmarks(&self) -> Result<Vec<Bookmark>, StoreError> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM bookmarks WHERE deleted_at IS NOT NULL ORDER BY deleted_at DESC, id DESC",
+            BOOKMARK_COLUMNS
+        ))?;
+        let bookmarks = stmt.query_map([], bookmark_from_row)?;
+        let mut bookmarks_vec = Vec::new();
+        for bookmark in bookmarks {
+            bookmarks_vec.push(bookmark?);
+        }
+        Ok(bookmarks_vec)
+    }
 
-#[derive(Debug)]
-pub enum SqliteServiceError {
-    IdNotFound(i32),
-    PathNotFound(String),
-    BookmarkAlreadyExists,
-    InternalError,
-}
+    // Permanently delete the bookmarks in the trash, returning how many there were
+    fn empty_trash(&self) -> Result<usize, StoreError> {
+        let count = self
+            .conn
+            .execute("DELETE FROM bookmarks WHERE deleted_at IS NOT NULL", [])?;
+        self.delete_unused_tags()?;
+        Ok(count)
+    }
 
-impl SqliteServiceError {
-    pub fn message(&self) -> String {
-        match self {
-            SqliteServiceError::PathNotFound(path) => {
-                format!("Bookmark with path {} not found", path)
-            }
-            SqliteServiceError::IdNotFound(id) => format!("Bookmark with id {} not found", id),
-            SqliteServiceError::BookmarkAlreadyExists => "Bookmark already exists".to_string(),
-            SqliteServiceError::InternalError => "Internal error".to_string(),
+    //
+    // History
+    //
+
+    fn add_history_entry(&self, entry: &HistoryEntry) -> Result<i32, StoreError> {
+        self.conn.execute(
+            "INSERT INTO history (bookmark_id, action, before, after, created_at, undone,
+                command, user, undo_of, batch)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            rusqlite::params![
+                entry.bookmark_id,
+                entry.action.as_str(),
+                to_json(&entry.before),
+                to_json(&entry.after),
+                entry.created_at,
+                entry.undone,
+                entry.command,
+                entry.user,
+                entry.undo_of,
+                entry.batch,
+            ],
+        )?;
+        Ok(self.conn.last_insert_rowid() as i32)
+    }
+
+    // Oldest first, for a single bookmark if given
+    fn get_history(&self, bookmark_id: Option<i32>) -> Result<Vec<HistoryEntry>, StoreError> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT * FROM history WHERE ?1 IS NULL OR bookmark_id = ?1 ORDER BY id")?;
+        let entries = stmt.query_map([bookmark_id], history_entry_from_row)?;
+        let mut entries_vec = Vec::new();
+        for entry in entries {
+            entries_vec.push(entry?);
         }
+        Ok(entries_vec)
     }
-}
 
-impl std::fmt::Display for SqliteServiceError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "oxmark - SqliteServiceError: {}", self.message())
+    fn get_history_entry(&self, id: i32) -> Result<HistoryEntry, StoreError> {
+        let mut stmt = self.conn.prepare("SELECT * FROM history WHERE id = ?")?;
+        let entry = stmt.query_row([id], history_entry_from_row)?;
+        Ok(entry)
     }
-}
-impl std::error::Error for SqliteServiceError {}
 
-/*
- *
- * SQLite Repository
- *
- */
+    // The most recent change not undone yet, to a bookmark that still exists.
+    // Changes made by undo itself are skipped, so that undo keeps going back
+    fn get_last_undoable_history_entry(&self) -> Result<Option<HistoryEntry>, StoreError> {
+        let mut stmt = self.conn.prepare(
+            "SELECT * FROM history WHERE undone = 0 AND undo_of IS NULL
+                AND bookmark_id IN (SELECT id FROM bookmarks)
+                ORDER BY id DESC LIMIT 1",
+        )?;
+        let mut entries = stmt.query_map([], history_entry_from_row)?;
+        Ok(entries.next().transpose()?)
+    }
 
-#[derive(Debug)]
-pub struct SqliteRepository {
-    conn: Connection,
-}
+    fn set_history_entry_undone(&self, id: i32) -> Result<(), StoreError> {
+        self.conn
+            .execute("UPDATE history SET undone = 1 WHERE id = ?", [id])?;
+        Ok(())
+    }
 
-impl SqliteRepository {
-    pub fn new() -> Result<SqliteRepository, SqliteRepositoryError> {
-        // Get the $HOME environment variable
-        let home_dir = std::env::var("HOME").unwrap();
-        let db_dir_path = std::path::Path::new(&home_dir).join(DATABASE_DIR);
-        let db_path = std::path::Path::new(&db_dir_path).join(DATABASE_NAME);
+    fn record_visit(&self, id: i32, timestamp: i64) -> Result<(), StoreError> {
+        self.conn.execute(
+            "UPDATE bookmarks SET visit_count = visit_count + 1, last_visited = ? WHERE id = ?",
+            rusqlite::params![timestamp, id],
+        )?;
+        Ok(())
+    }
 
-        let mut result = Connection::open(db_path.clone());
+    // Full-text search over name, path and description, best matches first.
+    // The score is the bm25 rank, where lower is better
+    fn search_bookmarks(
+        &self,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<(Bookmark, f64)>, StoreError> {
+        let fts_query = get_fts_query(query);
+        if fts_query.is_empty() {
+            return Ok(vec![]);
+        }
 
-        // If the connection failed, try to create the database file
-        if let Err(_) = result {
-            std::fs::create_dir_all(db_dir_path).unwrap();
-            if !std::path::Path::new(&db_path).exists() {
-                let file_creation_result = std::fs::File::create(db_path.clone());
-                if let Err(e) = file_creation_result {
-                    return Err(SqliteRepositoryError::IoError(e));
-                }
+        // Matches in the name weigh the most, then the description, then the path
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {}, bm25(bookmarks_fts, 10.0, 1.0, 5.0) AS rank FROM bookmarks_fts
+                JOIN bookmarks ON bookmarks.id = bookmarks_fts.rowid
+                WHERE bookmarks_fts MATCH ? AND bookmarks.deleted_at IS NULL
+                ORDER BY rank LIMIT ?",
+            BOOKMARK_COLUMNS
+        ))?;
+        let results = stmt.query_map(rusqlite::params![fts_query, limit as i64], |row| {
+            Ok((bookmark_from_row(row)?, row.get("rank")?))
+        })?;
 
-                result = Connection::open(db_path);
-            }
+        let mut results_vec = Vec::new();
+        for result in results {
+            results_vec.push(result?);
         }
+        Ok(results_vec)
+    }
 
-        // If the connection failed, return an error
-        if let Err(_) = result {
-            return Err(SqliteRepositoryError::ConnectionError);
+    //
+    // Tags
+    //
+
+    fn get_all_tags(&self) -> Result<Vec<(String, i32)>, StoreError> {
+        let mut stmt = self.conn.prepare(
+            "SELECT tags.name, count(bookmark_tags.bookmark_id) FROM tags
+                JOIN bookmark_tags ON bookmark_tags.tag_id = tags.id
+                JOIN bookmarks ON bookmarks.id = bookmark_tags.bookmark_id
+                WHERE bookmarks.deleted_at IS NULL
+                GROUP BY tags.id ORDER BY tags.name",
+        )?;
+        let tags = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        let mut tags_vec = Vec::new();
+        for tag in tags {
+            tags_vec.push(tag?);
         }
+        Ok(tags_vec)
+    }
 
-        let conn = result.unwrap();
-
-        // Create the table if it doesn't exist
-        let query_result = conn.execute(
-            "CREATE TABLE IF NOT EXISTS bookmarks (
-                id INTEGER PRIMARY KEY,
-                name TEXT,
-                path TEXT,
-                description TEXT
-            )",
-            [],
-        );
+    fn add_tag(&self, bookmark_id: i32, tag: &str) -> Result<(), StoreError> {
+        self.conn
+            .execute("INSERT OR IGNORE INTO tags (name) VALUES (?)", [tag])?;
+        self.conn.execute(
+            "INSERT OR IGNORE INTO bookmark_tags (bookmark_id, tag_id)
+                SELECT ?, id FROM tags WHERE name = ?",
+            rusqlite::params![bookmark_id, tag],
+        )?;
+        Ok(())
+    }
 
-        if let Err(e) = query_result {
-            return Err(SqliteRepositoryError::QueryError(e));
+    // Replace all the tags of a bookmark
+    fn set_tags(&self, bookmark_id: i32, tags: &[String]) -> Result<(), StoreError> {
+        self.conn.execute(
+            "DELETE FROM bookmark_tags WHERE bookmark_id = ?",
+            [bookmark_id],
+        )?;
+        for tag in tags {
+            self.add_tag(bookmark_id, tag)?;
         }
+        self.delete_unused_tags()?;
+        Ok(())
+    }
 
-        Ok(SqliteRepository { conn })
+    fn remove_tag(&self, bookmark_id: i32, tag: &str) -> Result<(), StoreError> {
+        self.conn.execute(
+            "DELETE FROM bookmark_tags WHERE bookmark_id = ?
+                AND tag_id = (SELECT id FROM tags WHERE name = ?)",
+            rusqlite::params![bookmark_id, tag],
+        )?;
+        self.delete_unused_tags()?;
+        Ok(())
     }
-    pub fn get_all_bookmarks(&self) -> Result<Vec<Bookmark>, SqliteRepositoryError> {
-        let mut bookmarks_vec: Vec<Bookmark> = Vec::new();
-        let mut stmt = self.conn.prepare("SELECT * FROM bookmarks")?;
-        let bookmarks = stmt.query_map([], |row| {
-            Ok(Bookmark {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                path: row.get(2)?,
-                description: row.get(3)?,
-            })
-        })?;
-        for bookmark in bookmarks {
-            bookmarks_vec.push(bookmark.unwrap());
+
+    // Renaming onto an existing tag merges the two. Run it in a transaction
+    fn rename_tag(&self, old_name: &str, new_name: &str) -> Result<(), StoreError> {
+        let old_id: i32 =
+            self.conn
+                .query_row("SELECT id FROM tags WHERE name = ?", [old_name], |row| {
+                    row.get(0)
+                })?;
+        // Deleting the old tag would remove it from every bookmark
+        if old_name == new_name {
+            return Ok(());
         }
-        Ok(bookmarks_vec)
-    }
-    pub fn get_bookmark(&self, id: i32) -> Result<Bookmark, SqliteRepositoryError> {
-        let mut stmt = self.conn.prepare("SELECT * FROM bookmarks WHERE id = ?")?;
-        let bookmark = stmt.query_row(&[&id], |row| {
-            Ok(Bookmark {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                path: row.get(2)?,
-                description: row.get(3)?,
-            })
-        })?;
-        Ok(bookmark)
+
+        self.conn
+            .execute("INSERT OR IGNORE INTO tags (name) VALUES (?)", [new_name])?;
+        self.conn.execute(
+            "INSERT OR IGNORE INTO bookmark_tags (bookmark_id, tag_id)
+                SELECT bookmark_id, (SELECT id FROM tags WHERE name = ?) FROM bookmark_tags
+                WHERE tag_id = ?",
+            rusqlite::params![new_name, old_id],
+        )?;
+        self.conn
+            .execute("DELETE FROM tags WHERE id = ?", [old_id])?;
+
+        Ok(())
     }
 
-    pub fn get_bookmark_by_path(&self, path: String) -> Result<Bookmark, SqliteRepositoryError> {
-        let mut stmt = self
-            .conn
-            .prepare("SELECT * FROM bookmarks WHERE path = ?")?;
-        let bookmark = stmt.query_row(&[&path], |row| {
-            Ok(Bookmark {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                path: row.get(2)?,
-                description: row.get(3)?,
-            })
-        })?;
-        Ok(bookmark)
+    //
+    // Folders
+    //
+
+    fn get_folder_id(&self, path: &str) -> Result<i32, StoreError> {
+        let id = self.conn.query_row(
+            "SELECT id FROM folder_paths WHERE path = ?",
+            [path],
+            |row| row.get(0),
+        )?;
+        Ok(id)
     }
 
-    pub fn create_bookmark(&self, bookmark: Bookmark) -> Result<(), SqliteRepositoryError> {
-        let mut stmt = self
-            .conn
-            .prepare("INSERT INTO bookmarks (name, path, description) VALUES (?, ?, ?)")?;
-        stmt.execute(&[&bookmark.name, &bookmark.path, &bookmark.description])?;
-        Ok(())
+    fn create_folder(&self, name: &str, parent_id: Option<i32>) -> Result<i32, StoreError> {
+        self.conn.execute(
+            "INSERT INTO folders (name, parent_id) VALUES (?, ?)",
+            rusqlite::params![name, parent_id],
+        )?;
+        Ok(self.conn.last_insert_rowid() as i32)
     }
 
-    pub fn update_bookmark(
-        &self,
-        id: i32,
-        bookmark: Bookmark,
-    ) -> Result<(), SqliteRepositoryError> {
-        let mut stmt = self
-            .conn
-            .prepare("UPDATE bookmarks SET name = ?, path = ?, description = ? WHERE id = ?")?;
-        stmt.execute(&[
-            &bookmark.name,
-            &bookmark.path,
-            &bookmark.description,
-            &Option::from(id.to_string()),
-        ])?;
+    fn move_folder(&self, id: i32, parent_id: Option<i32>) -> Result<(), StoreError> {
+        self.conn.execute(
+            "UPDATE folders SET parent_id = ? WHERE id = ?",
+            rusqlite::params![parent_id, id],
+        )?;
         Ok(())
     }
 
-    pub fn delete_bookmark(&self, id: i32) -> Result<(), SqliteRepositoryError> {
-        let mut stmt = self.conn.prepare("DELETE FROM bookmarks WHERE id = ?")?;
-        stmt.execute(&[&id])?;
-        Ok(())
+    // The full path of every folder, with the number of bookmarks directly inside it
+    fn get_folders(&self) -> Result<Vec<(String, i32)>, StoreError> {
+        let mut stmt = self.conn.prepare(
+            "SELECT folder_paths.path, count(bookmarks.id) FROM folder_paths
+                LEFT JOIN bookmarks ON bookmarks.folder_id = folder_paths.id
+                    AND bookmarks.deleted_at IS NULL
+                GROUP BY folder_paths.id",
+        )?;
+        let folders = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        let mut folders_vec = Vec::new();
+        for folder in folders {
+            folders_vec.push(folder?);
+        }
+        Ok(folders_vec)
     }
 }
 
+// Columns read by `bookmark_from_row`, selected by name so that adding
+// columns to the schema does not shift the row indices
+const BOOKMARK_COLUMNS: &str = "bookmarks.id, bookmarks.name, bookmarks.path,
+    bookmarks.description, bookmarks.visit_count, bookmarks.last_visited,
+    bookmarks.created_at, bookmarks.updated_at, bookmarks.last_seen_existing,
+    bookmarks.deleted_at, bookmarks.line, bookmarks.column, bookmarks.kind,
+    bookmarks.pre_hook, bookmarks.post_hook, bookmarks.env, bookmarks.working_directory,
+    (SELECT path FROM folder_paths WHERE folder_paths.id = bookmarks.folder_id) AS folder,
+    (SELECT group_concat(tags.name, ',') FROM bookmark_tags
+        JOIN tags ON tags.id = bookmark_tags.tag_id
+        WHERE bookmark_tags.bookmark_id = bookmarks.id) AS tags";
+
+fn bookmark_from_row(row: &rusqlite::Row) -> rusqlite::Result<Bookmark> {
+    // Tags are aggregated into a single comma separated column
+    let tags: Option<String> = row.get("tags")?;
+    let mut tags: Vec<String> = tags
+        .unwrap_or_default()
+        .split(',')
+        .filter(|tag| !tag.is_empty())
+        .map(|tag| tag.to_string())
+        .collect();
+    tags.sort();
+    let kind: String = row.get("kind")?;
+    let env: Option<String> = row.get("env")?;
+
+    Ok(Bookmark {
+        id: row.get("id")?,
+        name: row.get("name")?,
+        path: row.get("path")?,
+        description: row.get("description")?,
+        tags,
+        folder: row.get("folder")?,
+        visit_count: row.get("visit_count")?,
+        last_visited: row.get("last_visited")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+        last_seen_existing: row.get("last_seen_existing")?,
+        deleted_at: row.get("deleted_at")?,
+        line: row.get("line")?,
+        column: row.get("column")?,
+        kind: BookmarkKind::parse(&kind).unwrap_or_default(),
+        pre_hook: row.get("pre_hook")?,
+        post_hook: row.get("post_hook")?,
+        env: env
+            .unwrap_or_default()
+            .lines()
+            .map(|entry| entry.to_string())
+            .collect(),
+        working_directory: row.get("working_directory")?,
+    })
+}
+
+// Variables are stored one per line, and no variables as NULL
+fn get_env_column(env: &[String]) -> Option<String> {
+    (!env.is_empty()).then(|| env.join("\n"))
+}
+
+// Turn user input into an FTS5 query, matching every word as a prefix.
+// Words are quoted so that FTS5 syntax characters are matched literally
+fn get_fts_query(input: &str) -> String {
+    input
+        .split_whitespace()
+        .map(|word| format!("\"{}\"*", word.replace('"', "\"\"")))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn history_entry_from_row(row: &rusqlite::Row) -> rusqlite::Result<HistoryEntry> {
+    let action: String = row.get("action")?;
+    let before: Option<String> = row.get("before")?;
+    let after: Option<String> = row.get("after")?;
+
+    Ok(HistoryEntry {
+        id: row.get("id")?,
+        bookmark_id: row.get("bookmark_id")?,
+        action: HistoryAction::parse(&action).unwrap_or(HistoryAction::Update),
+        before: before.and_then(|json| serde_json::from_str(&json).ok()),
+        after: after.and_then(|json| serde_json::from_str(&json).ok()),
+        created_at: row.get("created_at")?,
+        undone: row.get("undone")?,
+        command: row.get("command")?,
+        user: row.get("user")?,
+        undo_of: row.get("undo_of")?,
+        batch: row.get("batch")?,
+    })
+}
+
+// Serializing a bookmark cannot fail, it only holds strings and numbers
+fn to_json(bookmark: &Option<Bookmark>) -> Option<String> {
+    bookmark
+        .as_ref()
+        .and_then(|bookmark| serde_json::to_string(bookmark).ok())
+}
+
 //
 // SqliteRepositoryError
 //
 
 #[derive(Debug)]
 pub enum SqliteRepositoryError {
-    NotFound,
     InternalError(rusqlite::Error),
     ConnectionError,
-    IoError(std::io::Error),
-    QueryError(rusqlite::Error),
+    DatabaseTooNew(i32, i32),
+    MigrationError(i32, rusqlite::Error),
+    BackupError(rusqlite::Error),
 }
 
 impl SqliteRepositoryError {
     pub fn message(&self) -> String {
         match self {
-            SqliteRepositoryError::NotFound => "Bookmark not found".to_string(),
             SqliteRepositoryError::InternalError(e) => format!("Internal error: {}", e),
             SqliteRepositoryError::ConnectionError => "Connection error".to_string(),
-            SqliteRepositoryError::IoError(e) => format!("IO error: {}", e),
-            SqliteRepositoryError::QueryError(e) => format!("Query error: {}", e),
+            SqliteRepositoryError::DatabaseTooNew(found, supported) => format!(
+                "Database schema version {} is newer than the supported version {}, please upgrade oxmark",
+                found, supported
+            ),
+            SqliteRepositoryError::MigrationError(version, e) => {
+                format!("Migration to schema version {} failed: {}", version, e)
+            }
+            SqliteRepositoryError::BackupError(e) => {
+                format!("Could not back up the database before migrating: {}", e)
+            }
         }
     }
 }
@@ -349,10 +673,7 @@ impl std::fmt::Display for SqliteRepositoryError {
 
 impl From<rusqlite::Error> for SqliteRepositoryError {
     fn from(e: rusqlite::Error) -> Self {
-        match e {
-            rusqlite::Error::QueryReturnedNoRows => SqliteRepositoryError::NotFound,
-            _ => SqliteRepositoryError::InternalError(e),
-        }
+        SqliteRepositoryError::InternalError(e)
     }
 }
 
@@ -0,0 +1,834 @@
+use std::cell::{Cell, RefCell};
+use std::path::Path;
+
+use super::toml_file::{Changes, Lock, StoreFiles};
+use super::{BookmarkStore, StoreError};
+use crate::repair::FileId;
+use crate::service::HistoryEntry;
+use crate::Bookmark;
+
+// Search weights of the name, the description and the path, like the bm25 weights of SQLite
+const NAME_WEIGHT: f64 = 10.0;
+const DESCRIPTION_WEIGHT: f64 = 5.0;
+const PATH_WEIGHT: f64 = 1.0;
+
+// Bookmarks kept in memory, and saved to a TOML file after every change when opened from one.
+// Without a file nothing is saved, which is only meant for tests
+#[derive(Debug)]
+pub struct MemoryStore {
+    data: RefCell<StoreData>,
+
+    // The data as it was when the current transaction began, restored on rollback
+    snapshot: RefCell<Option<StoreData>>,
+
+    files: Option<StoreFiles>,
+
+    // Held by transactions from their beginning to their end, so that
+    // no other process writes to the files in between
+    lock: RefCell<Option<Lock>>,
+
+    // What was changed and not saved yet, by the current transaction
+    changes: Cell<Changes>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct StoreData {
+    pub records: Vec<Record>,
+    pub folders: Vec<Folder>,
+    pub history: Vec<HistoryEntry>,
+}
+
+// A bookmark along with what is only known to the store. The folder and
+// tags of the bookmark are kept by the record and filled in when read
+#[derive(Debug, Clone)]
+pub struct Record {
+    pub bookmark: Bookmark,
+    pub folder_id: Option<i32>,
+    pub file_id: Option<FileId>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Folder {
+    pub id: i32,
+    pub name: String,
+    pub parent_id: Option<i32>,
+}
+
+impl MemoryStore {
+    #[cfg(test)]
+    pub fn new() -> MemoryStore {
+        MemoryStore {
+            data: RefCell::new(StoreData::default()),
+            snapshot: RefCell::new(None),
+            files: None,
+            lock: RefCell::new(None),
+            changes: Cell::new(Changes::default()),
+        }
+    }
+
+    // Load the bookmarks of a TOML file, saved again after every change
+    pub fn open_file(path: &Path) -> Result<MemoryStore, StoreError> {
+        let (files, data) = StoreFiles::open(path)?;
+
+        Ok(MemoryStore {
+            data: RefCell::new(data),
+            snapshot: RefCell::new(None),
+            files: Some(files),
+            lock: RefCell::new(None),
+            changes: Cell::new(Changes::default()),
+        })
+    }
+
+    fn in_transaction(&self) -> bool {
+        self.snapshot.borrow().is_some()
+    }
+
+    // The files may have been changed by hand or by another oxmark since they were read
+    fn reload(&self) -> Result<(), StoreError> {
+        match &self.files {
+            Some(files) if !self.in_transaction() => files.reload(&mut self.data.borrow_mut()),
+            _ => Ok(()),
+        }
+    }
+
+    fn lock(&self) -> Result<Option<Lock>, StoreError> {
+        self.files.as_ref().map(StoreFiles::lock).transpose()
+    }
+
+    fn read<T>(
+        &self,
+        query: impl FnOnce(&StoreData) -> Result<T, StoreError>,
+    ) -> Result<T, StoreError> {
+        self.reload()?;
+        query(&self.data.borrow())
+    }
+
+    fn write<T>(
+        &self,
+        change: impl FnOnce(&mut StoreData) -> Result<T, StoreError>,
+    ) -> Result<T, StoreError> {
+        self.change(
+            Changes {
+                bookmarks: true,
+                history: false,
+            },
+            change,
+        )
+    }
+
+    fn write_history<T>(
+        &self,
+        change: impl FnOnce(&mut StoreData) -> Result<T, StoreError>,
+    ) -> Result<T, StoreError> {
+        self.change(
+            Changes {
+                bookmarks: false,
+                history: true,
+            },
+            change,
+        )
+    }
+
+    // Outside of transactions, the change is made to the latest data while holding the lock
+    fn change<T>(
+        &self,
+        changes: Changes,
+        change: impl FnOnce(&mut StoreData) -> Result<T, StoreError>,
+    ) -> Result<T, StoreError> {
+        let _lock = match self.in_transaction() {
+            true => None,
+            false => self.lock()?,
+        };
+        self.reload()?;
+
+        let result = change(&mut self.data.borrow_mut())?;
+        let pending = self.changes.get();
+        self.changes.set(Changes {
+            bookmarks: pending.bookmarks || changes.bookmarks,
+            history: pending.history || changes.history,
+        });
+
+        if !self.in_transaction() {
+            self.save()?;
+        }
+        Ok(result)
+    }
+
+    fn save(&self) -> Result<(), StoreError> {
+        let changes = self.changes.take();
+        match &self.files {
+            Some(files) => files.save(&self.data.borrow(), changes),
+            None => Ok(()),
+        }
+    }
+}
+
+impl BookmarkStore for MemoryStore {
+    // Transactions do not nest, like in SQLite
+    fn begin(&self) -> Result<(), StoreError> {
+        if self.in_transaction() {
+            return Err(StoreError::FileError(
+                "A transaction is already in progress".to_string(),
+            ));
+        }
+        *self.lock.borrow_mut() = self.lock()?;
+        if let Err(e) = self.reload() {
+            self.lock.borrow_mut().take();
+            return Err(e);
+        }
+        *self.snapshot.borrow_mut() = Some(self.data.borrow().clone());
+        Ok(())
+    }
+
+    // The lock is released once the changes are saved
+    fn commit(&self) -> Result<(), StoreError> {
+        self.snapshot.borrow_mut().take();
+        let result = self.save();
+        self.lock.borrow_mut().take();
+        result
+    }
+
+    fn rollback(&self) -> Result<(), StoreError> {
+        if let Some(snapshot) = self.snapshot.borrow_mut().take() {
+            *self.data.borrow_mut() = snapshot;
+        }
+        self.changes.take();
+        self.lock.borrow_mut().take();
+        Ok(())
+    }
+
+    fn get_all_bookmarks(&self) -> Result<Vec<Bookmark>, StoreError> {
+        self.read(|data| {
+            Ok(data
+                .records
+                .iter()
+                .filter(|record| record.bookmark.deleted_at.is_none())
+                .map(|record| data.to_bookmark(record))
+                .collect())
+        })
+    }
+
+    fn get_bookmark(&self, id: i32) -> Result<Bookmark, StoreError> {
+        self.read(|data| {
+            let record = data.record(id)?;
+            match record.bookmark.deleted_at {
+                Some(_) => Err(StoreError::NotFound),
+                None => Ok(data.to_bookmark(record)),
+            }
+        })
+    }
+
+    fn get_any_bookmark(&self, id: i32) -> Result<Bookmark, StoreError> {
+        self.read(|data| Ok(data.to_bookmark(data.record(id)?)))
+    }
+
+    fn get_bookmark_by_path(&self, path: String) -> Result<Bookmark, StoreError> {
+        self.read(|data| {
+            data.find(|bookmark| bookmark.path.as_ref() == Some(&path))
+                .ok_or(StoreError::NotFound)
+        })
+    }
+
+    fn get_bookmark_at(
+        &self,
+        path: &str,
+        line: Option<u32>,
+        column: Option<u32>,
+    ) -> Result<Bookmark, StoreError> {
+        self.read(|data| {
+            data.find(|bookmark| {
+                bookmark.path.as_deref() == Some(path)
+                    && bookmark.line == line
+                    && bookmark.column == column
+            })
+            .ok_or(StoreError::NotFound)
+        })
+    }
+
+    fn get_bookmark_by_name(&self, name: &str) -> Result<Bookmark, StoreError> {
+        self.read(|data| {
+            data.find(|bookmark| bookmark.name.as_deref() == Some(name))
+                .ok_or(StoreError::NotFound)
+        })
+    }
+
+    fn create_bookmark(&self, bookmark: Bookmark) -> Result<i32, StoreError> {
+        self.write(|data| {
+            let id = match bookmark.id {
+                Some(id) if data.record(id).is_ok() => return Err(StoreError::DuplicateId(id)),
+                Some(id) => id,
+                None => {
+                    data.records
+                        .iter()
+                        .map(|record| record.bookmark.id.unwrap_or(0))
+                        .max()
+                        .unwrap_or(0)
+                        + 1
+                }
+            };
+            let folder_id = data.existing_folder_id(&bookmark.folder)?;
+
+            data.records.push(Record {
+                bookmark: Bookmark {
+                    id: Some(id),
+                    tags: vec![],
+                    folder: None,
+                    deleted_at: None,
+                    ..bookmark
+                },
+                folder_id,
+                file_id: None,
+            });
+            data.records.sort_by_key(|record| record.bookmark.id);
+            Ok(id)
+        })
+    }
+
+    fn update_bookmark(&self, id: i32, bookmark: Bookmark) -> Result<(), StoreError> {
+        self.write(|data| {
+            let folder_id = data.existing_folder_id(&bookmark.folder)?;
+            if let Ok(record) = data.record_mut(id) {
+                let stored = &mut record.bookmark;
+                stored.name = bookmark.name;
+                stored.path = bookmark.path;
+                stored.description = bookmark.description;
+                stored.updated_at = bookmark.updated_at;
+                stored.last_seen_existing = bookmark.last_seen_existing;
+                stored.line = bookmark.line;
+                stored.column = bookmark.column;
+                stored.kind = bookmark.kind;
+                stored.pre_hook = bookmark.pre_hook;
+                stored.post_hook = bookmark.post_hook;
+                stored.env = bookmark.env;
+                stored.working_directory = bookmark.working_directory;
+                record.folder_id = folder_id;
+            }
+            Ok(())
+        })
+    }
+
+    fn delete_bookmark(&self, id: i32) -> Result<(), StoreError> {
+        self.write(|data| {
+            data.records.retain(|record| record.bookmark.id != Some(id));
+            Ok(())
+        })
+    }
+
+    fn record_visit(&self, id: i32, timestamp: i64) -> Result<(), StoreError> {
+        self.write(|data| {
+            if let Ok(record) = data.record_mut(id) {
+                record.bookmark.visit_count += 1;
+                record.bookmark.last_visited = Some(timestamp);
+            }
+            Ok(())
+        })
+    }
+
+    // Every word of the query has to start a word of the name, path or description
+    fn search_bookmarks(
+        &self,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<(Bookmark, f64)>, StoreError> {
+        let query = query.to_lowercase();
+        let query_words: Vec<&str> = get_words(&query).collect();
+        if query_words.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut results: Vec<(Bookmark, f64)> = self
+            .get_all_bookmarks()?
+            .into_iter()
+            .filter_map(|bookmark| {
+                let fields = [
+                    (bookmark.name.clone(), NAME_WEIGHT),
+                    (bookmark.description.clone(), DESCRIPTION_WEIGHT),
+                    (bookmark.path.clone(), PATH_WEIGHT),
+                ];
+                let mut score = 0.0;
+                for query_word in &query_words {
+                    let word_score: f64 = fields
+                        .iter()
+                        .filter(|(field, _)| has_word_starting_with(field, query_word))
+                        .map(|(_, weight)| weight)
+                        .sum();
+                    if word_score == 0.0 {
+                        return None;
+                    }
+                    score += word_score;
+                }
+                Some((bookmark, -score))
+            })
+            .collect();
+
+        results.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+        results.truncate(limit);
+        Ok(results)
+    }
+
+    fn update_last_seen_existing(
+        &self,
+        files: &[(i32, FileId)],
+        timestamp: i64,
+    ) -> Result<(), StoreError> {
+        self.write(|data| {
+            for (id, file_id) in files {
+                if let Ok(record) = data.record_mut(*id) {
+                    record.bookmark.last_seen_existing = Some(timestamp);
+                    record.file_id = Some(*file_id);
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn set_file_id(&self, id: i32, file_id: FileId) -> Result<(), StoreError> {
+        self.write(|data| {
+            if let Ok(record) = data.record_mut(id) {
+                record.file_id = Some(file_id);
+            }
+            Ok(())
+        })
+    }
+
+    fn get_file_id(&self, id: i32) -> Result<Option<FileId>, StoreError> {
+        self.read(|data| Ok(data.record(id)?.file_id))
+    }
+
+    fn trash_bookmark(&self, id: i32, timestamp: i64) -> Result<(), StoreError> {
+        self.write(|data| {
+            if let Ok(record) = data.record_mut(id) {
+                record.bookmark.deleted_at = Some(timestamp);
+            }
+            Ok(())
+        })
+    }
+
+    fn restore_bookmark(&self, id: i32) -> Result<(), StoreError> {
+        self.write(|data| {
+            if let Ok(record) = data.record_mut(id) {
+                record.bookmark.deleted_at = None;
+            }
+            Ok(())
+        })
+    }
+
+    fn get_trashed_bookmarks(&self) -> Result<Vec<Bookmark>, StoreError> {
+        self.read(|data| {
+            let mut bookmarks: Vec<Bookmark> = data
+                .records
+                .iter()
+                .filter(|record| record.bookmark.deleted_at.is_some())
+                .map(|record| data.to_bookmark(record))
+                .collect();
+            bookmarks.sort_by_key(|bookmark| std::cmp::Reverse((bookmark.deleted_at, bookmark.id)));
+            Ok(bookmarks)
+        })
+    }
+
+    fn empty_trash(&self) -> Result<usize, StoreError> {
+        self.write(|data| {
+            let count = data.records.len();
+            data.records
+                .retain(|record| record.bookmark.deleted_at.is_none());
+            Ok(count - data.records.len())
+        })
+    }
+
+    fn add_history_entry(&self, entry: &HistoryEntry) -> Result<i32, StoreError> {
+        self.write_history(|data| {
+            let id = data
+                .history
+                .iter()
+                .filter_map(|entry| entry.id)
+                .max()
+                .unwrap_or(0)
+                + 1;
+            data.history.push(HistoryEntry {
+                id: Some(id),
+                ..entry.clone()
+            });
+            Ok(id)
+        })
+    }
+
+    fn get_history(&self, bookmark_id: Option<i32>) -> Result<Vec<HistoryEntry>, StoreError> {
+        self.read(|data| {
+            Ok(data
+                .history
+                .iter()
+                .filter(|entry| bookmark_id.is_none_or(|id| entry.bookmark_id == id))
+                .cloned()
+                .collect())
+        })
+    }
+
+    fn get_history_entry(&self, id: i32) -> Result<HistoryEntry, StoreError> {
+        self.read(|data| {
+            data.history
+                .iter()
+                .find(|entry| entry.id == Some(id))
+                .cloned()
+                .ok_or(StoreError::NotFound)
+        })
+    }
+
+    fn get_last_undoable_history_entry(&self) -> Result<Option<HistoryEntry>, StoreError> {
+        self.read(|data| {
+            Ok(data
+                .history
+                .iter()
+                .rev()
+                .find(|entry| {
+                    !entry.undone
+                        && entry.undo_of.is_none()
+                        && data.record(entry.bookmark_id).is_ok()
+                })
+                .cloned())
+        })
+    }
+
+    fn set_history_entry_undone(&self, id: i32) -> Result<(), StoreError> {
+        self.write_history(|data| {
+            if let Some(entry) = data.history.iter_mut().find(|entry| entry.id == Some(id)) {
+                entry.undone = true;
+            }
+            Ok(())
+        })
+    }
+
+    fn get_all_tags(&self) -> Result<Vec<(String, i32)>, StoreError> {
+        self.read(|data| {
+            let mut tags: Vec<(String, i32)> = vec![];
+            let bookmark_tags = data
+                .records
+                .iter()
+                .filter(|record| record.bookmark.deleted_at.is_none())
+                .flat_map(|record| &record.bookmark.tags);
+            for tag in bookmark_tags {
+                match tags.iter_mut().find(|(name, _)| name == tag) {
+                    Some((_, count)) => *count += 1,
+                    None => tags.push((tag.clone(), 1)),
+                }
+            }
+            tags.sort();
+            Ok(tags)
+        })
+    }
+
+    fn add_tag(&self, bookmark_id: i32, tag: &str) -> Result<(), StoreError> {
+        self.write(|data| {
+            if let Ok(record) = data.record_mut(bookmark_id) {
+                if !record.bookmark.tags.iter().any(|existing| existing == tag) {
+                    record.bookmark.tags.push(tag.to_string());
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn set_tags(&self, bookmark_id: i32, tags: &[String]) -> Result<(), StoreError> {
+        self.write(|data| {
+            if let Ok(record) = data.record_mut(bookmark_id) {
+                record.bookmark.tags.clear();
+                for tag in tags {
+                    if !record.bookmark.tags.contains(tag) {
+                        record.bookmark.tags.push(tag.clone());
+                    }
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn remove_tag(&self, bookmark_id: i32, tag: &str) -> Result<(), StoreError> {
+        self.write(|data| {
+            if let Ok(record) = data.record_mut(bookmark_id) {
+                record.bookmark.tags.retain(|existing| existing != tag);
+            }
+            Ok(())
+        })
+    }
+
+    fn rename_tag(&self, old_name: &str, new_name: &str) -> Result<(), StoreError> {
+        self.write(|data| {
+            let tagged_records = data
+                .records
+                .iter_mut()
+                .filter(|record| record.bookmark.tags.iter().any(|tag| tag == old_name));
+
+            let mut found = false;
+            for record in tagged_records {
+                found = true;
+                // Like in SQLite, renaming a tag to itself changes nothing
+                if old_name == new_name {
+                    continue;
+                }
+                let tags = &mut record.bookmark.tags;
+                tags.retain(|tag| tag != old_name && tag != new_name);
+                tags.push(new_name.to_string());
+            }
+            match found {
+                true => Ok(()),
+                false => Err(StoreError::NotFound),
+            }
+        })
+    }
+
+    fn get_folder_id(&self, path: &str) -> Result<i32, StoreError> {
+        self.read(|data| data.folder_id(path).ok_or(StoreError::NotFound))
+    }
+
+    fn create_folder(&self, name: &str, parent_id: Option<i32>) -> Result<i32, StoreError> {
+        self.write(|data| {
+            let id = data
+                .folders
+                .iter()
+                .map(|folder| folder.id)
+                .max()
+                .unwrap_or(0)
+                + 1;
+            data.folders.push(Folder {
+                id,
+                name: name.to_string(),
+                parent_id,
+            });
+            Ok(id)
+        })
+    }
+
+    fn move_folder(&self, id: i32, parent_id: Option<i32>) -> Result<(), StoreError> {
+        self.write(|data| {
+            if let Some(folder) = data.folders.iter_mut().find(|folder| folder.id == id) {
+                folder.parent_id = parent_id;
+            }
+            Ok(())
+        })
+    }
+
+    fn get_folders(&self) -> Result<Vec<(String, i32)>, StoreError> {
+        self.read(|data| {
+            Ok(data
+                .folders
+                .iter()
+                .filter_map(|folder| {
+                    let count = data
+                        .records
+                        .iter()
+                        .filter(|record| {
+                            record.folder_id == Some(folder.id)
+                                && record.bookmark.deleted_at.is_none()
+                        })
+                        .count();
+                    Some((data.folder_path(folder.id)?, count as i32))
+                })
+                .collect())
+        })
+    }
+}
+
+impl StoreData {
+    fn record(&self, id: i32) -> Result<&Record, StoreError> {
+        self.records
+            .iter()
+            .find(|record| record.bookmark.id == Some(id))
+            .ok_or(StoreError::NotFound)
+    }
+
+    fn record_mut(&mut self, id: i32) -> Result<&mut Record, StoreError> {
+        self.records
+            .iter_mut()
+            .find(|record| record.bookmark.id == Some(id))
+            .ok_or(StoreError::NotFound)
+    }
+
+    // The first bookmark outside the trash matching the predicate, records being sorted by id
+    fn find(&self, predicate: impl Fn(&Bookmark) -> bool) -> Option<Bookmark> {
+        self.records
+            .iter()
+            .find(|record| record.bookmark.deleted_at.is_none() && predicate(&record.bookmark))
+            .map(|record| self.to_bookmark(record))
+    }
+
+    // The bookmark with its folder path, and its tags sorted
+    pub fn to_bookmark(&self, record: &Record) -> Bookmark {
+        let mut bookmark = record.bookmark.clone();
+        bookmark.folder = record.folder_id.and_then(|id| self.folder_path(id));
+        bookmark.tags.sort();
+        bookmark
+    }
+
+    // The full "a/b/c" path of a folder. None for folders inside a parent that does not
+    // exist, or inside themselves, which a hand edited file may hold
+    pub fn folder_path(&self, id: i32) -> Option<String> {
+        let mut names = vec![];
+        let mut current = Some(id);
+        while let Some(id) = current {
+            if names.len() > self.folders.len() {
+                return None;
+            }
+            let folder = self.folders.iter().find(|folder| folder.id == id)?;
+            names.push(folder.name.as_str());
+            current = folder.parent_id;
+        }
+        names.reverse();
+        Some(names.join("/"))
+    }
+
+    // The id of the folder of a bookmark, which must exist
+    fn existing_folder_id(&self, folder: &Option<String>) -> Result<Option<i32>, StoreError> {
+        folder
+            .as_ref()
+            .map(|folder| {
+                self.folder_id(folder)
+                    .ok_or(StoreError::FolderNotFound(folder.clone()))
+            })
+            .transpose()
+    }
+
+    pub fn folder_id(&self, path: &str) -> Option<i32> {
+        self.folders
+            .iter()
+            .find(|folder| self.folder_path(folder.id).as_deref() == Some(path))
+            .map(|folder| folder.id)
+    }
+}
+
+// Words are split on anything but letters and digits, and compared without case
+fn has_word_starting_with(field: &Option<String>, prefix: &str) -> bool {
+    let field = field.as_deref().unwrap_or_default().to_lowercase();
+    let matches = get_words(&field).any(|word| word.starts_with(prefix));
+    matches
+}
+
+fn get_words(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bookmark(path: &str) -> Bookmark {
+        Bookmark {
+            path: Some(path.to_string()),
+            ..Bookmark::default()
+        }
+    }
+
+    #[test]
+    fn rollback_restores_the_data() {
+        let store = MemoryStore::new();
+        let store: &dyn BookmarkStore = &store;
+        store.create_bookmark(bookmark("/kept")).unwrap();
+
+        let result: Result<(), StoreError> = store.transaction(|store| {
+            store.create_bookmark(bookmark("/dropped"))?;
+            Err(StoreError::NotFound)
+        });
+
+        assert!(result.is_err());
+        let paths: Vec<_> = store
+            .get_all_bookmarks()
+            .unwrap()
+            .into_iter()
+            .filter_map(|bookmark| bookmark.path)
+            .collect();
+        assert_eq!(paths, ["/kept"]);
+    }
+
+    #[test]
+    fn transactions_do_not_nest() {
+        let store = MemoryStore::new();
+        store.begin().unwrap();
+        assert!(store.begin().is_err());
+    }
+
+    #[test]
+    fn rename_tag_to_itself_changes_nothing() {
+        let store = MemoryStore::new();
+        let id = store.create_bookmark(bookmark("/tagged")).unwrap();
+        store
+            .set_tags(id, &["rust".to_string(), "work".to_string()])
+            .unwrap();
+
+        store.rename_tag("rust", "rust").unwrap();
+
+        assert_eq!(store.get_bookmark(id).unwrap().tags, ["rust", "work"]);
+        assert!(matches!(
+            store.rename_tag("missing", "missing"),
+            Err(StoreError::NotFound)
+        ));
+    }
+
+    fn temporary_file(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("oxmark-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join("oxmark.toml")
+    }
+
+    fn paths(store: &dyn BookmarkStore) -> Vec<String> {
+        store
+            .get_all_bookmarks()
+            .unwrap()
+            .into_iter()
+            .filter_map(|bookmark| bookmark.path)
+            .collect()
+    }
+
+    #[test]
+    fn changes_made_by_another_process_are_kept() {
+        let path = temporary_file("reload");
+        let first = MemoryStore::open_file(&path).unwrap();
+        let second = MemoryStore::open_file(&path).unwrap();
+
+        first.create_bookmark(bookmark("/one")).unwrap();
+        second.create_bookmark(bookmark("/two")).unwrap();
+        first.create_bookmark(bookmark("/three")).unwrap();
+
+        let reopened = MemoryStore::open_file(&path).unwrap();
+        assert_eq!(paths(&reopened), ["/one", "/two", "/three"]);
+    }
+
+    #[test]
+    fn visits_leave_the_history_alone() {
+        let path = temporary_file("history");
+        let store = MemoryStore::open_file(&path).unwrap();
+        let id = store.create_bookmark(bookmark("/visited")).unwrap();
+        store
+            .add_history_entry(&HistoryEntry::new(
+                crate::service::HistoryAction::Create,
+                None,
+                Some(store.get_bookmark(id).unwrap()),
+            ))
+            .unwrap();
+        let history_path = crate::store::toml_file::history_path(&path);
+        let history = std::fs::read_to_string(&history_path).unwrap();
+        assert_eq!(history.lines().count(), 1);
+        assert!(!std::fs::read_to_string(&path).unwrap().contains("history"));
+
+        std::fs::write(&history_path, format!("{}\n", history)).unwrap();
+        store.record_visit(id, 1).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&history_path).unwrap(),
+            format!("{}\n", history)
+        );
+    }
+
+    #[test]
+    fn transactions_hold_the_lock() {
+        let path = temporary_file("lock");
+        let store = MemoryStore::open_file(&path).unwrap();
+        let lock_path = path.with_extension("toml.lock");
+
+        store.begin().unwrap();
+        assert!(lock_path.exists());
+        store.create_bookmark(bookmark("/locked")).unwrap();
+        store.commit().unwrap();
+
+        assert!(!lock_path.exists());
+        assert_eq!(paths(&MemoryStore::open_file(&path).unwrap()), ["/locked"]);
+    }
+}
@@ -0,0 +1,230 @@
+// Where the bookmarks are kept. The services and the TUI only go through the
+// `BookmarkStore` trait, so that any backend can be used in their place
+
+use std::path::Path;
+
+use crate::config::Backend;
+use crate::repair::FileId;
+use crate::service::HistoryEntry;
+use crate::sqlite::{SqliteRepository, SqliteRepositoryError};
+use crate::Bookmark;
+
+mod memory;
+mod toml_file;
+
+pub use memory::MemoryStore;
+
+// Bookmarks in the trash are only returned by the methods saying so. Changes are
+// saved right away, unless they are made inside a transaction
+pub trait BookmarkStore: std::fmt::Debug {
+    //
+    // Transactions, see `transaction` and `rollback_transaction`
+    //
+
+    fn begin(&self) -> Result<(), StoreError>;
+    fn commit(&self) -> Result<(), StoreError>;
+    fn rollback(&self) -> Result<(), StoreError>;
+
+    //
+    // Bookmarks
+    //
+
+    fn get_all_bookmarks(&self) -> Result<Vec<Bookmark>, StoreError>;
+    fn get_bookmark(&self, id: i32) -> Result<Bookmark, StoreError>;
+    // Including the bookmarks in the trash
+    fn get_any_bookmark(&self, id: i32) -> Result<Bookmark, StoreError>;
+    fn get_bookmark_by_path(&self, path: String) -> Result<Bookmark, StoreError>;
+    // The bookmark of a path at exactly that line and column, none meaning the whole file
+    fn get_bookmark_at(
+        &self,
+        path: &str,
+        line: Option<u32>,
+        column: Option<u32>,
+    ) -> Result<Bookmark, StoreError>;
+    // The oldest bookmark with that name
+    fn get_bookmark_by_name(&self, name: &str) -> Result<Bookmark, StoreError>;
+    // Returns the id of the new bookmark, which is picked by the store unless given.
+    // Tags are added separately, and the folder must exist already
+    fn create_bookmark(&self, bookmark: Bookmark) -> Result<i32, StoreError>;
+    // Updates everything but the visits, the creation time and the tags
+    fn update_bookmark(&self, id: i32, bookmark: Bookmark) -> Result<(), StoreError>;
+    fn delete_bookmark(&self, id: i32) -> Result<(), StoreError>;
+    fn record_visit(&self, id: i32, timestamp: i64) -> Result<(), StoreError>;
+    // Full-text search over name, path and description, best matches first.
+    // The score is negative, lower being better, like the bm25 rank of SQLite
+    fn search_bookmarks(
+        &self,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<(Bookmark, f64)>, StoreError>;
+
+    //
+    // Existence checks
+    //
+
+    fn update_last_seen_existing(
+        &self,
+        files: &[(i32, FileId)],
+        timestamp: i64,
+    ) -> Result<(), StoreError>;
+    fn set_file_id(&self, id: i32, file_id: FileId) -> Result<(), StoreError>;
+    // The file the bookmark pointed to when it was last seen existing
+    fn get_file_id(&self, id: i32) -> Result<Option<FileId>, StoreError>;
+
+    //
+    // Trash
+    //
+
+    fn trash_bookmark(&self, id: i32, timestamp: i64) -> Result<(), StoreError>;
+    fn restore_bookmark(&self, id: i32) -> Result<(), StoreError>;
+    // Most recently deleted first
+    fn get_trashed_bookmarks(&self) -> Result<Vec<Bookmark>, StoreError>;
+    // Permanently delete the bookmarks in the trash, returning how many there were
+    fn empty_trash(&self) -> Result<usize, StoreError>;
+
+    //
+    // History
+    //
+
+    // Returns the id of the new entry
+    fn add_history_entry(&self, entry: &HistoryEntry) -> Result<i32, StoreError>;
+    // Oldest first, for a single bookmark if given
+    fn get_history(&self, bookmark_id: Option<i32>) -> Result<Vec<HistoryEntry>, StoreError>;
+    fn get_history_entry(&self, id: i32) -> Result<HistoryEntry, StoreError>;
+    // The most recent change not undone yet, to a bookmark that still exists.
+    // Changes made by undo itself are skipped, so that undo keeps going back
+    fn get_last_undoable_history_entry(&self) -> Result<Option<HistoryEntry>, StoreError>;
+    fn set_history_entry_undone(&self, id: i32) -> Result<(), StoreError>;
+
+    //
+    // Tags
+    //
+
+    // Every tag of a bookmark outside the trash, with the number of such bookmarks
+    fn get_all_tags(&self) -> Result<Vec<(String, i32)>, StoreError>;
+    fn add_tag(&self, bookmark_id: i32, tag: &str) -> Result<(), StoreError>;
+    // Replace all the tags of a bookmark
+    fn set_tags(&self, bookmark_id: i32, tags: &[String]) -> Result<(), StoreError>;
+    fn remove_tag(&self, bookmark_id: i32, tag: &str) -> Result<(), StoreError>;
+    // Renaming onto an existing tag merges the two. Run it in a transaction
+    fn rename_tag(&self, old_name: &str, new_name: &str) -> Result<(), StoreError>;
+
+    //
+    // Folders
+    //
+
+    fn get_folder_id(&self, path: &str) -> Result<i32, StoreError>;
+    fn create_folder(&self, name: &str, parent_id: Option<i32>) -> Result<i32, StoreError>;
+    fn move_folder(&self, id: i32, parent_id: Option<i32>) -> Result<(), StoreError>;
+    // The full path of every folder, with the number of bookmarks directly inside it
+    fn get_folders(&self) -> Result<Vec<(String, i32)>, StoreError>;
+}
+
+impl dyn BookmarkStore + '_ {
+    // Run the given operations in a single transaction, rolled back if they fail
+    pub fn transaction<T, E>(
+        &self,
+        operations: impl FnOnce(&dyn BookmarkStore) -> Result<T, E>,
+    ) -> Result<T, E>
+    where
+        E: From<StoreError>,
+    {
+        self.begin()?;
+        match operations(self) {
+            Ok(result) => {
+                self.commit()?;
+                Ok(result)
+            }
+            Err(e) => {
+                self.rollback()?;
+                Err(e)
+            }
+        }
+    }
+
+    // Run the given operations in a transaction that is always rolled back
+    pub fn rollback_transaction<T, E>(
+        &self,
+        operations: impl FnOnce(&dyn BookmarkStore) -> Result<T, E>,
+    ) -> Result<T, E>
+    where
+        E: From<StoreError>,
+    {
+        self.begin()?;
+        let result = operations(self);
+        self.rollback()?;
+        result
+    }
+}
+
+// Open the store of the given backend at the path, creating it if missing
+pub fn open(backend: Backend, path: &Path) -> Result<Box<dyn BookmarkStore>, StoreError> {
+    Ok(match backend {
+        Backend::Sqlite => Box::new(SqliteRepository::new(path)?),
+        Backend::Toml => Box::new(MemoryStore::open_file(path)?),
+    })
+}
+
+// Delete the store of the given backend at the path, along with the files kept next to it
+pub fn remove(backend: Backend, path: &Path) -> std::io::Result<()> {
+    std::fs::remove_file(path)?;
+    if backend == Backend::Toml {
+        return match std::fs::remove_file(toml_file::history_path(path)) {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        };
+    }
+    Ok(())
+}
+
+//
+// StoreError
+//
+
+#[derive(Debug)]
+pub enum StoreError {
+    NotFound,
+    DuplicateId(i32),
+    FolderNotFound(String),
+    // Errors specific to the SQLite database
+    DatabaseError(SqliteRepositoryError),
+    IoError(std::io::Error),
+    // The TOML file could not be read or written
+    FileError(String),
+}
+
+impl StoreError {
+    pub fn message(&self) -> String {
+        match self {
+            StoreError::NotFound => "Bookmark not found".to_string(),
+            StoreError::DuplicateId(id) => format!("A bookmark with id {} already exists", id),
+            StoreError::FolderNotFound(path) => format!("Folder {} not found", path),
+            StoreError::DatabaseError(e) => e.message(),
+            StoreError::IoError(e) => format!("IO error: {}", e),
+            StoreError::FileError(message) => message.clone(),
+        }
+    }
+}
+
+impl std::fmt::Display for StoreError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "oxmark - StoreError: {}", self.message())
+    }
+}
+
+impl From<SqliteRepositoryError> for StoreError {
+    fn from(e: SqliteRepositoryError) -> Self {
+        StoreError::DatabaseError(e)
+    }
+}
+
+impl From<rusqlite::Error> for StoreError {
+    fn from(e: rusqlite::Error) -> Self {
+        match e {
+            rusqlite::Error::QueryReturnedNoRows => StoreError::NotFound,
+            _ => StoreError::DatabaseError(SqliteRepositoryError::InternalError(e)),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {}
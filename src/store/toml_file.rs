@@ -0,0 +1,348 @@
+// The TOML file of the toml backend, meant to be readable and editable by hand.
+// Bookmarks added by hand may leave out the id and anything but the path. The
+// history only grows, so it is kept out of the way in a JSON Lines file next to it
+
+use std::cell::Cell;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use super::memory::{Folder, Record, StoreData};
+use super::StoreError;
+use crate::repair::FileId;
+use crate::service::HistoryEntry;
+use crate::Bookmark;
+
+const HEADER: &str = "# Oxmark bookmarks, rewritten by oxmark after every change\n\n";
+
+// How long to wait for another oxmark to finish writing
+const LOCK_ATTEMPTS: u32 = 50;
+const LOCK_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+// The bookmarks file and the history file next to it, along with their state when they
+// were last read or written, to notice the changes made by other processes
+#[derive(Debug)]
+pub struct StoreFiles {
+    path: PathBuf,
+    history_path: PathBuf,
+    versions: Cell<(Option<Version>, Option<Version>)>,
+}
+
+// The modification time and the size of a file. The size catches changes made
+// within the resolution of the modification time
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Version {
+    modified: SystemTime,
+    size: u64,
+}
+
+// The parts of the data changed since they were last saved
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Changes {
+    pub bookmarks: bool,
+    pub history: bool,
+}
+
+// Held while the files are read and written, removed when dropped
+#[derive(Debug)]
+pub struct Lock {
+    path: PathBuf,
+}
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+impl StoreFiles {
+    // Like a SQLite database, a missing file is created right away
+    pub fn open(path: &Path) -> Result<(StoreFiles, StoreData), StoreError> {
+        let files = StoreFiles {
+            path: path.to_path_buf(),
+            history_path: history_path(path),
+            versions: Cell::new((None, None)),
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(StoreError::IoError)?;
+        }
+
+        let _lock = files.lock()?;
+        let mut data = StoreData::default();
+        if !path.exists() {
+            files.save(
+                &data,
+                Changes {
+                    bookmarks: true,
+                    history: false,
+                },
+            )?;
+        }
+        files.reload(&mut data)?;
+        Ok((files, data))
+    }
+
+    // Wait for the other processes writing to the files to be done. The lock is a file
+    // created next to the bookmarks, left behind if oxmark is killed while holding it
+    pub fn lock(&self) -> Result<Lock, StoreError> {
+        let path = self.path.with_extension("toml.lock");
+        for _ in 0..LOCK_ATTEMPTS {
+            let file = std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path);
+            match file {
+                Ok(mut file) => {
+                    let _ = write!(file, "{}", std::process::id());
+                    return Ok(Lock { path });
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    std::thread::sleep(LOCK_RETRY_DELAY)
+                }
+                Err(e) => return Err(StoreError::IoError(e)),
+            }
+        }
+        Err(StoreError::FileError(format!(
+            "{} is used by another oxmark, remove {} if none is running",
+            self.path.display(),
+            path.display()
+        )))
+    }
+
+    // Read again the files changed since they were last read or written
+    pub fn reload(&self, data: &mut StoreData) -> Result<(), StoreError> {
+        let (bookmarks_version, history_version) = self.versions.get();
+
+        let current_version = version(&self.path);
+        if current_version.is_some() && current_version != bookmarks_version {
+            let history = std::mem::take(&mut data.history);
+            *data = StoreData {
+                history,
+                ..load(&self.path)?
+            };
+        }
+        let current_history_version = version(&self.history_path);
+        if current_history_version != history_version {
+            data.history = load_history(&self.history_path)?;
+        }
+
+        self.versions
+            .set((current_version, current_history_version));
+        Ok(())
+    }
+
+    // Only the changed files are written
+    pub fn save(&self, data: &StoreData, changes: Changes) -> Result<(), StoreError> {
+        let (mut bookmarks_version, mut history_version) = self.versions.get();
+        if changes.bookmarks {
+            save(&self.path, data)?;
+            bookmarks_version = version(&self.path);
+        }
+        if changes.history {
+            save_history(&self.history_path, &data.history)?;
+            history_version = version(&self.history_path);
+        }
+        self.versions.set((bookmarks_version, history_version));
+        Ok(())
+    }
+}
+
+// The history of oxmark.toml is kept in oxmark.history.jsonl
+pub fn history_path(path: &Path) -> PathBuf {
+    path.with_extension("history.jsonl")
+}
+
+fn version(path: &Path) -> Option<Version> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some(Version {
+        modified: metadata.modified().ok()?,
+        size: metadata.len(),
+    })
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct TomlDocument {
+    // Full paths such as "work/backend", parents are created when missing
+    folders: Vec<String>,
+    bookmarks: Vec<Bookmark>,
+    // The file each bookmark was last seen pointing to, see `repair`
+    files: Vec<TomlFileId>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TomlFileId {
+    id: i32,
+    inode: i64,
+    device: i64,
+}
+
+fn load(path: &Path) -> Result<StoreData, StoreError> {
+    let content = std::fs::read_to_string(path).map_err(StoreError::IoError)?;
+    let document: TomlDocument = toml::from_str(&content)
+        .map_err(|e| StoreError::FileError(format!("Invalid {}: {}", path.display(), e)))?;
+
+    let mut data = StoreData::default();
+    for folder in &document.folders {
+        ensure_folder(&mut data, folder);
+    }
+
+    let mut next_id = document
+        .bookmarks
+        .iter()
+        .filter_map(|bookmark| bookmark.id)
+        .max()
+        .unwrap_or(0)
+        + 1;
+    for mut bookmark in document.bookmarks {
+        let id = match bookmark.id {
+            Some(id)
+                if data
+                    .records
+                    .iter()
+                    .any(|record| record.bookmark.id == Some(id)) =>
+            {
+                return Err(StoreError::FileError(format!(
+                    "Invalid {}: the id {} is used by several bookmarks",
+                    path.display(),
+                    id
+                )))
+            }
+            Some(id) => id,
+            None => {
+                next_id += 1;
+                next_id - 1
+            }
+        };
+        bookmark.id = Some(id);
+
+        let folder_id = bookmark
+            .folder
+            .take()
+            .and_then(|folder| ensure_folder(&mut data, &folder));
+        let file_id = document
+            .files
+            .iter()
+            .find(|file| file.id == id)
+            .map(|file| FileId {
+                inode: file.inode as u64,
+                device: file.device as u64,
+            });
+        data.records.push(Record {
+            bookmark,
+            folder_id,
+            file_id,
+        });
+    }
+    data.records.sort_by_key(|record| record.bookmark.id);
+
+    Ok(data)
+}
+
+fn save(path: &Path, data: &StoreData) -> Result<(), StoreError> {
+    let mut folders: Vec<String> = data
+        .folders
+        .iter()
+        .filter_map(|folder| data.folder_path(folder.id))
+        .collect();
+    folders.sort();
+
+    let document = TomlDocument {
+        folders,
+        bookmarks: data
+            .records
+            .iter()
+            .map(|record| data.to_bookmark(record))
+            .collect(),
+        files: data
+            .records
+            .iter()
+            .filter_map(|record| {
+                let file_id = record.file_id?;
+                Some(TomlFileId {
+                    id: record.bookmark.id?,
+                    inode: file_id.inode as i64,
+                    device: file_id.device as i64,
+                })
+            })
+            .collect(),
+    };
+    let content = toml::to_string(&document)
+        .map_err(|e| StoreError::FileError(format!("Could not write {}: {}", path.display(), e)))?;
+
+    write_file(path, &format!("{}{}", HEADER, content))
+}
+
+// One entry per line, oldest first. A missing file is an empty history
+fn load_history(path: &Path) -> Result<Vec<HistoryEntry>, StoreError> {
+    let content = match std::fs::read_to_string(path) {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        result => result.map_err(StoreError::IoError)?,
+    };
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            serde_json::from_str(line)
+                .map_err(|e| StoreError::FileError(format!("Invalid {}: {}", path.display(), e)))
+        })
+        .collect()
+}
+
+fn save_history(path: &Path, history: &[HistoryEntry]) -> Result<(), StoreError> {
+    let mut content = String::new();
+    for entry in history {
+        let line = serde_json::to_string(entry).map_err(|e| {
+            StoreError::FileError(format!("Could not write {}: {}", path.display(), e))
+        })?;
+        content.push_str(&line);
+        content.push('\n');
+    }
+    write_file(path, &content)
+}
+
+// Written next to the file first, so that a failed write never leaves half a file
+fn write_file(path: &Path, content: &str) -> Result<(), StoreError> {
+    let mut temporary_path = path.as_os_str().to_owned();
+    temporary_path.push(".tmp");
+    std::fs::write(&temporary_path, content)
+        .and_then(|_| std::fs::rename(&temporary_path, path))
+        .map_err(StoreError::IoError)
+}
+
+// The id of the folder with the given path, created along with its parents if missing
+fn ensure_folder(data: &mut StoreData, path: &str) -> Option<i32> {
+    let mut parent_id = None;
+    let mut current_path = String::new();
+
+    for name in path.split('/').filter(|name| !name.is_empty()) {
+        if !current_path.is_empty() {
+            current_path.push('/');
+        }
+        current_path.push_str(name);
+
+        let id = match data.folder_id(&current_path) {
+            Some(id) => id,
+            None => {
+                let id = data
+                    .folders
+                    .iter()
+                    .map(|folder| folder.id)
+                    .max()
+                    .unwrap_or(0)
+                    + 1;
+                data.folders.push(Folder {
+                    id,
+                    name: name.to_string(),
+                    parent_id,
+                });
+                id
+            }
+        };
+        parent_id = Some(id);
+    }
+
+    parent_id
+}
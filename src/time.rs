@@ -0,0 +1,175 @@
+// Timestamps are stored as seconds since the Unix epoch, and displayed in UTC
+
+const MINUTE: i64 = 60;
+const HOUR: i64 = 60 * MINUTE;
+const DAY: i64 = 24 * HOUR;
+const WEEK: i64 = 7 * DAY;
+
+pub fn now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+// Format a timestamp as "YYYY-MM-DD HH:MM"
+pub fn format(timestamp: i64) -> String {
+    let days = timestamp.div_euclid(DAY);
+    let seconds = timestamp.rem_euclid(DAY);
+    let (year, month, day) = civil_from_days(days);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year,
+        month,
+        day,
+        seconds / HOUR,
+        seconds % HOUR / MINUTE
+    )
+}
+
+// Parse a point in time given on the command line. Accepts dates ("2024-05-01"),
+// dates with a time ("2024-05-01 13:30" or "2024-05-01T13:30:00") and
+// durations relative to now ("90m", "12h", "7d", "2w")
+pub fn parse(input: &str) -> Result<i64, String> {
+    let input = input.trim();
+
+    if let Some(time) = parse_duration(input).and_then(|duration| now().checked_sub(duration)) {
+        return Ok(time);
+    }
+
+    parse_date_time(input).ok_or(format!(
+        "invalid time '{}', expected YYYY-MM-DD[ HH:MM[:SS]] or a duration like 7d",
+        input
+    ))
+}
+
+fn parse_duration(input: &str) -> Option<i64> {
+    let unit = match input.chars().last()? {
+        's' => 1,
+        'm' => MINUTE,
+        'h' => HOUR,
+        'd' => DAY,
+        'w' => WEEK,
+        _ => return None,
+    };
+    let amount: i64 = input[..input.len() - 1].parse().ok()?;
+    // Durations too large to be a time are rejected rather than wrapping around
+    amount.checked_mul(unit)
+}
+
+fn parse_date_time(input: &str) -> Option<i64> {
+    let (date, time) = match input.split_once(['T', ' ']) {
+        Some((date, time)) => (date, Some(time)),
+        None => (input, None),
+    };
+
+    let mut date_parts = date.splitn(3, '-');
+    let year: i64 = date_parts.next()?.parse().ok()?;
+    let month: u32 = date_parts.next()?.parse().ok()?;
+    let day: u32 = date_parts.next()?.parse().ok()?;
+    if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+        return None;
+    }
+
+    let mut seconds = 0;
+    if let Some(time) = time {
+        let mut time_parts = time.splitn(3, ':');
+        let hours: i64 = time_parts.next()?.parse().ok()?;
+        let minutes: i64 = time_parts.next()?.parse().ok()?;
+        let secs: i64 = time_parts.next().unwrap_or("0").parse().ok()?;
+        if hours > 23 || minutes > 59 || secs > 59 {
+            return None;
+        }
+        seconds = hours * HOUR + minutes * MINUTE + secs;
+    }
+
+    Some(days_from_civil(year, month, day) * DAY + seconds)
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Conversions between days since the epoch and proleptic Gregorian dates,
+// see http://howardhinnant.github.io/date_algorithms.html
+
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_dates() {
+        let cases = [
+            ("2024-05-01", Some("2024-05-01 00:00")),
+            ("2024-05-01 13:30", Some("2024-05-01 13:30")),
+            ("2024-05-01T13:30:59", Some("2024-05-01 13:30")),
+            ("2024-02-29", Some("2024-02-29 00:00")),
+            ("2000-02-29", Some("2000-02-29 00:00")),
+            ("2024-01-31", Some("2024-01-31 00:00")),
+            ("2024-02-30", None),
+            ("2023-02-29", None),
+            ("1900-02-29", None),
+            ("2024-04-31", None),
+            ("2024-13-01", None),
+            ("2024-05-00", None),
+            ("2024-05-01 24:00", None),
+            ("2024-05", None),
+            ("yesterday", None),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(
+                parse(input).ok().map(format),
+                expected.map(String::from),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn parse_durations() {
+        let before = now();
+        let time = parse("2h").unwrap();
+        assert!((before - 2 * HOUR..=now() - 2 * HOUR).contains(&time));
+        assert_eq!(parse_duration("3w"), Some(3 * WEEK));
+        assert_eq!(parse_duration("90m"), Some(90 * MINUTE));
+        assert_eq!(parse_duration("7x"), None);
+        assert_eq!(parse_duration(&format!("{}d", i64::MAX)), None);
+    }
+}
@@ -0,0 +1,120 @@
+// Loads the git status of bookmarked directories on a background thread,
+// so that slow repositories never hold up typing
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+
+use crate::git::GitStatus;
+
+#[derive(Debug)]
+enum Request {
+    // The selected bookmark, loaded before anything already queued
+    Selected(String),
+    // A bookmark in the list, loaded for its dirty marker
+    Listed(String),
+}
+
+#[derive(Debug)]
+pub struct GitStatuses {
+    // Loaded statuses, None for directories outside of a work tree
+    statuses: HashMap<String, Option<GitStatus>>,
+
+    // Paths sent to the worker, loaded or not, so that each is only loaded once
+    requested: HashSet<String>,
+
+    // Paths already sent as the selection, which may have been queued as listed before
+    requested_as_selected: HashSet<String>,
+
+    sender: Sender<Request>,
+    receiver: Receiver<(String, Option<GitStatus>)>,
+}
+
+impl GitStatuses {
+    pub fn new() -> GitStatuses {
+        let (sender, requests) = mpsc::channel();
+        let (results, receiver) = mpsc::channel();
+        std::thread::spawn(move || load_statuses(requests, results));
+
+        GitStatuses {
+            statuses: HashMap::new(),
+            requested: HashSet::new(),
+            requested_as_selected: HashSet::new(),
+            sender,
+            receiver,
+        }
+    }
+
+    // The status of the directory, None while loading or outside of a work tree
+    pub fn get(&self, path: &str) -> Option<&GitStatus> {
+        self.statuses.get(path)?.as_ref()
+    }
+
+    pub fn request_selected(&mut self, path: &str) {
+        if self.statuses.contains_key(path) || !self.requested_as_selected.insert(path.to_string())
+        {
+            return;
+        }
+        self.requested.insert(path.to_string());
+        let _ = self.sender.send(Request::Selected(path.to_string()));
+    }
+
+    pub fn request_listed(&mut self, path: &str) {
+        if !self.requested.insert(path.to_string()) {
+            return;
+        }
+        let _ = self.sender.send(Request::Listed(path.to_string()));
+    }
+
+    // Take in the statuses loaded since the last call, returning whether there were any
+    pub fn receive(&mut self) -> bool {
+        let mut has_received = false;
+        while let Ok((path, status)) = self.receiver.try_recv() {
+            self.statuses.insert(path, status);
+            has_received = true;
+        }
+        has_received
+    }
+}
+
+// The worker: the selection jumps the queue, and each path is loaded once.
+// Stops when the app drops its end of the channels
+fn load_statuses(requests: Receiver<Request>, results: Sender<(String, Option<GitStatus>)>) {
+    let mut queue: VecDeque<String> = VecDeque::new();
+    let mut loaded: HashSet<String> = HashSet::new();
+
+    loop {
+        // Wait for work only when there is nothing left to do
+        let request = match queue.is_empty() {
+            true => requests.recv().ok(),
+            false => match requests.try_recv() {
+                Ok(request) => Some(request),
+                Err(TryRecvError::Empty) => None,
+                Err(TryRecvError::Disconnected) => return,
+            },
+        };
+        match request {
+            Some(Request::Selected(path)) => {
+                queue.push_front(path);
+                continue;
+            }
+            Some(Request::Listed(path)) => {
+                queue.push_back(path);
+                continue;
+            }
+            None if queue.is_empty() => return,
+            None => {}
+        }
+
+        let Some(path) = queue.pop_front() else {
+            continue;
+        };
+        if !loaded.insert(path.clone()) {
+            continue;
+        }
+        let status = crate::git::get_status(&PathBuf::from(&path));
+        if results.send((path, status)).is_err() {
+            return;
+        }
+    }
+}
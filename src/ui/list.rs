@@ -1,33 +1,53 @@
 use super::Bookmark;
-use crate::ui::App;
+use crate::ui::{App, SearchMode, TreeRow};
+use crate::BookmarkKind;
 
 use ratatui::layout::{Flex, Layout, Offset};
 use ratatui::prelude::Stylize;
 use ratatui::prelude::{Constraint, Margin};
 use ratatui::style::{Color, Style};
 use ratatui::text::{Line, Span};
-use ratatui::widgets::{Block, Borders, ListItem, Paragraph};
+use ratatui::widgets::{Block, Borders, ListItem, Paragraph, Wrap};
 
 const DEFAULT_BLOCK: ratatui::widgets::Block =
     ratatui::widgets::Block::bordered().border_type(ratatui::widgets::BorderType::Rounded);
-const TITLE: &str = "Oxmark";
 const BOOKMARK_TITLE_WIDTH: usize = 20;
+const TAG_STYLE: Style = Style::new().fg(Color::Magenta);
+const FOLDER_STYLE: Style = Style::new().fg(Color::Yellow);
+const TREE_INDENT: &str = "  ";
+const POSITION_STYLE: Style = Style::new().fg(Color::Cyan);
+const TAB_WIDTH: usize = 4;
+const URL_STYLE: Style = Style::new().fg(Color::LightCyan);
+const HOOK_STYLE: Style = Style::new().fg(Color::LightGreen);
+const COMMAND_STYLE: Style = Style::new().fg(Color::LightYellow);
+const REMOTE_STYLE: Style = Style::new().fg(Color::LightMagenta);
+const GIT_BRANCH_STYLE: Style = Style::new().fg(Color::LightRed);
+const GIT_DIRTY_STYLE: Style = Style::new().fg(Color::Yellow);
+const GIT_DIRTY_MARKER: &str = " ●";
 
 pub fn render(app: &App, frame: &mut ratatui::Frame) {
-    let bookmarks_vec = app.filtered_bookmarks.clone().unwrap_or_else(Vec::new);
+    let bookmarks_vec = app.filtered_bookmarks.clone().unwrap_or_default();
 
     // Create the elements
     let search_bar = app.search_bar.clone().unwrap();
 
-    let list_items = bookmarks_vec
-        .iter()
-        .map(|bookmark| {
-            bookmark.to_list_item(
-                app,
-                bookmarks_vec.iter().position(|b| b == bookmark).unwrap(),
-            )
-        })
-        .collect::<Vec<ListItem>>();
+    let list_items = match &app.tree_rows {
+        Some(tree_rows) => tree_rows
+            .iter()
+            .enumerate()
+            .map(|(index, row)| get_tree_list_item(app, row, index))
+            .collect::<Vec<ListItem>>(),
+        None => bookmarks_vec
+            .iter()
+            .map(|bookmark| {
+                bookmark.to_list_item(
+                    app,
+                    bookmarks_vec.iter().position(|b| b == bookmark).unwrap(),
+                    0,
+                )
+            })
+            .collect::<Vec<ListItem>>(),
+    };
 
     let list = ratatui::widgets::List::new(list_items);
 
@@ -39,10 +59,17 @@ pub fn render(app: &App, frame: &mut ratatui::Frame) {
     frame.render_widget(DEFAULT_BLOCK, layout_areas.main_area);
 
     // Render the title
-    frame.render_widget(Paragraph::new(TITLE).centered(), layout_areas.title_area);
+    frame.render_widget(
+        Paragraph::new(app.title()).centered(),
+        layout_areas.title_area,
+    );
 
-    // Render the search input block
-    frame.render_widget(DEFAULT_BLOCK, layout_areas.search_area);
+    // Render the search input block, titled with the search mode when it is not the default
+    let search_block = match app.search_mode {
+        SearchMode::Fuzzy => DEFAULT_BLOCK,
+        SearchMode::FullText => DEFAULT_BLOCK.title(" Full text "),
+    };
+    frame.render_widget(search_block, layout_areas.search_area);
 
     // Render the search bar
     frame.render_widget(
@@ -101,22 +128,95 @@ fn render_preview(
 
     frame.render_widget(DEFAULT_BLOCK, total_preview_area);
 
+    // Split the preview area between the bookmark details and the content preview
+    let details_lines = get_bookmark_details(app);
+    let [details_area, preview_area] = Layout::vertical([
+        Constraint::Length(details_lines.len() as u16),
+        Constraint::Fill(1),
+    ])
+    .areas(preview_area);
+
     // Render the title
     let bookmark_name = app
         .selected_bookmark
         .clone()
-        .unwrap_or(Bookmark::default())
+        .unwrap_or_default()
         .name
         .clone()
         .unwrap_or("".to_string());
     frame.render_widget(Paragraph::new(bookmark_name).centered(), title_area);
 
+    // Render the details
+    frame.render_widget(Paragraph::new(details_lines), details_area);
+
     // Render the preview
-    let preview_widget = get_bookmark_preview(app);
+    let preview_widget = get_bookmark_preview(app, preview_area.height);
     frame.render_widget(preview_widget, preview_area);
 }
 
-fn get_bookmark_preview(app: &App) -> ratatui::widgets::Paragraph {
+fn get_bookmark_details(app: &App) -> Vec<Line<'static>> {
+    let Some(bookmark) = app.selected_bookmark.as_ref() else {
+        return vec![];
+    };
+
+    let mut lines = vec![];
+
+    if let Some(folder) = &bookmark.folder {
+        lines.push(Line::from(vec![
+            Span::styled("Folder: ", Style::default().fg(Color::DarkGray)),
+            Span::styled(folder.clone(), FOLDER_STYLE),
+        ]));
+    }
+
+    if !bookmark.tags.is_empty() {
+        lines.push(Line::from(vec![
+            Span::styled("Tags: ", Style::default().fg(Color::DarkGray)),
+            Span::styled(get_tags_string(&bookmark.tags), TAG_STYLE),
+        ]));
+    }
+
+    let hooks = [
+        ("Pre: ", bookmark.pre_hook.clone()),
+        (
+            "Env: ",
+            (!bookmark.env.is_empty()).then(|| bookmark.env.join(" ")),
+        ),
+        ("Post: ", bookmark.post_hook.clone()),
+    ];
+    for (label, hook) in hooks {
+        if let Some(hook) = hook {
+            lines.push(Line::from(vec![
+                Span::styled(label, Style::default().fg(Color::DarkGray)),
+                Span::styled(hook, HOOK_STYLE),
+            ]));
+        }
+    }
+
+    let timestamps = [
+        ("Created: ", bookmark.created_at),
+        ("Updated: ", bookmark.updated_at),
+        ("Last seen: ", bookmark.last_seen_existing),
+    ];
+    for (label, timestamp) in timestamps {
+        if let Some(timestamp) = timestamp {
+            lines.push(Line::from(vec![
+                Span::styled(label, Style::default().fg(Color::DarkGray)),
+                Span::raw(crate::time::format(timestamp)),
+            ]));
+        }
+    }
+
+    lines
+}
+
+fn get_tags_string(tags: &[String]) -> String {
+    tags.iter()
+        .map(|tag| format!("#{}", tag))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn get_bookmark_preview(app: &App, height: u16) -> ratatui::widgets::Paragraph<'_> {
     if app.selected_bookmark.clone().is_none() {
         return ratatui::widgets::Paragraph::new("No bookmark selected")
             .style(Style::default().fg(Color::LightBlue))
@@ -126,28 +226,29 @@ fn get_bookmark_preview(app: &App) -> ratatui::widgets::Paragraph {
     let bookmark_path_string = app
         .selected_bookmark
         .clone()
-        .unwrap_or(Bookmark::default())
+        .unwrap_or_default()
         .path
         .clone()
         .unwrap_or("".to_string());
 
     let bookmark_path = std::path::Path::new(&bookmark_path_string);
-    if !bookmark_path.exists() {
+    let kind = app.selected_bookmark.as_ref().unwrap().kind;
+    if kind == BookmarkKind::Path && !bookmark_path.exists() {
         return ratatui::widgets::Paragraph::new("Path does not exist")
             .style(Style::default().fg(Color::Red))
             .centered();
     }
-    if bookmark_path.is_file() {
-        return ratatui::widgets::Paragraph::new("File preview not yet supported")
-            .style(Style::default().fg(Color::Blue))
-            .centered();
-    }
 
-    // Directory preview
-
-    // Get the directory contents
-
-    let preview = get_bookmark_directory_preview(app);
+    let preview = match kind {
+        BookmarkKind::Url => get_bookmark_url_preview(bookmark_path_string),
+        BookmarkKind::Command => get_bookmark_command_preview(app),
+        BookmarkKind::Remote => get_bookmark_remote_preview(app),
+        // The path line and the top border take two lines
+        BookmarkKind::Path if bookmark_path.is_file() => {
+            get_bookmark_file_preview(app, height.saturating_sub(2) as usize)
+        }
+        BookmarkKind::Path => get_bookmark_directory_preview(app),
+    };
 
     // Add the block style
     preview.block(
@@ -157,8 +258,8 @@ fn get_bookmark_preview(app: &App) -> ratatui::widgets::Paragraph {
     )
 }
 
-fn get_bookmark_directory_preview(app: &App) -> ratatui::widgets::Paragraph {
-    let selected_bookmark = app.selected_bookmark.clone().unwrap_or(Bookmark::default());
+fn get_bookmark_directory_preview(app: &App) -> ratatui::widgets::Paragraph<'_> {
+    let selected_bookmark = app.selected_bookmark.clone().unwrap_or_default();
 
     let bookmark_path_string = selected_bookmark.path.clone().unwrap_or("".to_string());
 
@@ -187,7 +288,7 @@ fn get_bookmark_directory_preview(app: &App) -> ratatui::widgets::Paragraph {
     filtered_directory_contents.sort_by(|a, b| a.name.cmp(&b.name));
 
     // Sort the directory contents by file type
-    filtered_directory_contents.sort_by(|a, b| a.is_file.cmp(&b.is_file));
+    filtered_directory_contents.sort_by_key(|a| a.is_file);
 
     // Set the last entry to be marked as last
     if let Some(last_entry) = filtered_directory_contents.last_mut() {
@@ -201,15 +302,230 @@ fn get_bookmark_directory_preview(app: &App) -> ratatui::widgets::Paragraph {
 
     let mut lines = vec![get_path_line(bookmark_path_string.clone())];
 
+    // Repositories get their state above the entries, once it has been loaded
+    if let Some(status) = app.git_statuses.get(&bookmark_path_string) {
+        lines.extend(get_git_status_lines(status));
+        lines.push(Line::default());
+    }
+
     lines.extend(directory_contents_lines);
 
-    return ratatui::widgets::Paragraph::new(lines);
+    ratatui::widgets::Paragraph::new(lines)
+}
+
+// The working directory, then the whole command wrapped over as many lines as needed
+fn get_bookmark_command_preview(app: &App) -> ratatui::widgets::Paragraph<'static> {
+    let selected_bookmark = app.selected_bookmark.clone().unwrap_or_default();
+
+    let mut lines = vec![];
+    if let Some(directory) = &selected_bookmark.working_directory {
+        let style = if std::path::Path::new(directory).is_dir() {
+            Style::default().fg(Color::DarkGray)
+        } else {
+            Style::default().fg(Color::Red)
+        };
+        lines.push(Line::from(Span::styled(format!("cd {}", directory), style)));
+    }
+    lines.extend(
+        selected_bookmark
+            .path
+            .unwrap_or_default()
+            .lines()
+            .map(|line| Line::from(Span::styled(line.to_string(), COMMAND_STYLE))),
+    );
+
+    ratatui::widgets::Paragraph::new(lines).wrap(Wrap { trim: false })
+}
+
+// The branch with its distance to the upstream, the changed files and the last commits
+fn get_git_status_lines(status: &crate::git::GitStatus) -> Vec<Line<'static>> {
+    let head = match (&status.branch, &status.commit) {
+        (Some(branch), _) => branch.clone(),
+        (None, Some(commit)) => format!("detached at {}", commit),
+        (None, None) => "detached".to_string(),
+    };
+    let mut branch_line = vec![Span::styled(format!(" {}", head), GIT_BRANCH_STYLE)];
+    if let Some((ahead, behind)) = status.ahead_behind {
+        branch_line.push(Span::styled(
+            format!(" ↑{} ↓{}", ahead, behind),
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+
+    let changes = if status.is_dirty() {
+        Span::styled(
+            format!(
+                "{} modified, {} untracked",
+                status.modified, status.untracked
+            ),
+            GIT_DIRTY_STYLE,
+        )
+    } else {
+        Span::styled("Clean", Style::default().fg(Color::Green))
+    };
+
+    let mut lines = vec![Line::from(branch_line), Line::from(changes)];
+    lines.extend(status.recent_commits.iter().map(|subject| {
+        Line::from(vec![
+            Span::styled("• ", Style::default().fg(Color::DarkGray)),
+            Span::raw(subject.clone()),
+        ])
+    }));
+    lines
+}
+
+// The host and the directory on it, which are not looked up so that the preview stays instant
+fn get_bookmark_remote_preview(app: &App) -> ratatui::widgets::Paragraph<'static> {
+    let selected_bookmark = app.selected_bookmark.clone().unwrap_or_default();
+    let Some((host, path)) = selected_bookmark.remote_target() else {
+        return ratatui::widgets::Paragraph::new("Invalid remote target")
+            .style(Style::default().fg(Color::Red));
+    };
+
+    let lines = vec![
+        get_path_line(selected_bookmark.path.clone().unwrap_or_default()),
+        Line::from(vec![
+            Span::styled("Host: ", Style::default().fg(Color::DarkGray)),
+            Span::styled(host, REMOTE_STYLE),
+        ]),
+        Line::from(vec![
+            Span::styled("Path: ", Style::default().fg(Color::DarkGray)),
+            Span::styled(path, REMOTE_STYLE),
+        ]),
+    ];
+    ratatui::widgets::Paragraph::new(lines)
+}
+
+// The parts of the URL, one per line
+fn get_bookmark_url_preview(url_string: String) -> ratatui::widgets::Paragraph<'static> {
+    let Ok(url) = url::Url::parse(&url_string) else {
+        return ratatui::widgets::Paragraph::new("Invalid URL")
+            .style(Style::default().fg(Color::Red));
+    };
+
+    let host = match url.port() {
+        Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+        None => url.host_str().unwrap_or_default().to_string(),
+    };
+    let parts = [
+        ("Scheme: ", Some(url.scheme().to_string())),
+        ("Host: ", Some(host)),
+        ("Path: ", Some(url.path().to_string())),
+        ("Query: ", url.query().map(|query| query.to_string())),
+        (
+            "Fragment: ",
+            url.fragment().map(|fragment| fragment.to_string()),
+        ),
+    ];
+
+    let mut lines = vec![get_path_line(url_string.clone())];
+    for (label, value) in parts {
+        if let Some(value) = value {
+            lines.push(Line::from(vec![
+                Span::styled(label, Style::default().fg(Color::DarkGray)),
+                Span::styled(value, URL_STYLE),
+            ]));
+        }
+    }
+    ratatui::widgets::Paragraph::new(lines)
+}
+
+// The lines of the file around the bookmarked line, which is centered and highlighted
+fn get_bookmark_file_preview(app: &App, height: usize) -> ratatui::widgets::Paragraph<'_> {
+    let selected_bookmark = app.selected_bookmark.clone().unwrap_or_default();
+    let bookmark_path_string = selected_bookmark.path.clone().unwrap_or("".to_string());
+
+    let line_index = (selected_bookmark.line.unwrap_or(1) as usize).saturating_sub(1);
+    let first_index = match selected_bookmark.line {
+        Some(_) => line_index.saturating_sub(height / 2),
+        None => 0,
+    };
+
+    let Ok(file) = std::fs::File::open(&bookmark_path_string) else {
+        return ratatui::widgets::Paragraph::new("Cannot read the file")
+            .style(Style::default().fg(Color::Red));
+    };
+    let mut file_lines = Vec::new();
+    for (index, line) in std::io::BufRead::split(std::io::BufReader::new(file), b'\n')
+        .take(first_index + height)
+        .enumerate()
+    {
+        let Ok(line) = line else {
+            break;
+        };
+        if line.contains(&0) {
+            return ratatui::widgets::Paragraph::new("Binary file")
+                .style(Style::default().fg(Color::Blue));
+        }
+        if index >= first_index {
+            file_lines.push(String::from_utf8_lossy(&line).trim_end().to_string());
+        }
+    }
+
+    let number_width = (first_index + file_lines.len()).to_string().len();
+    let mut lines = vec![get_path_line(bookmark_path_string.clone())];
+    lines.extend(file_lines.into_iter().enumerate().map(|(offset, text)| {
+        let index = first_index + offset;
+        let is_bookmarked = selected_bookmark.line.is_some() && index == line_index;
+        let column = selected_bookmark.column.filter(|_| is_bookmarked);
+        get_file_line(index + 1, number_width, &text, is_bookmarked, column)
+    }));
+
+    ratatui::widgets::Paragraph::new(lines)
+}
+
+fn get_file_line(
+    number: usize,
+    number_width: usize,
+    text: &str,
+    is_bookmarked: bool,
+    column: Option<u32>,
+) -> Line<'static> {
+    let text_style = if is_bookmarked {
+        POSITION_STYLE.bold()
+    } else {
+        Style::default().fg(Color::White)
+    };
+    let mut spans = vec![Span::styled(
+        format!("{:>width$} ", number, width = number_width),
+        Style::default().fg(Color::DarkGray),
+    )];
+
+    // The character at the bookmarked column is shown reversed
+    let expand_tabs = |text: String| text.replace('\t', &" ".repeat(TAB_WIDTH));
+    let characters: Vec<char> = text.chars().collect();
+    match column.map(|column| (column as usize).saturating_sub(1)) {
+        Some(column) if column < characters.len() => {
+            spans.push(Span::styled(
+                expand_tabs(characters[..column].iter().collect()),
+                text_style,
+            ));
+            spans.push(Span::styled(
+                expand_tabs(characters[column].to_string()),
+                text_style.reversed(),
+            ));
+            spans.push(Span::styled(
+                expand_tabs(characters[column + 1..].iter().collect()),
+                text_style,
+            ));
+        }
+        _ => spans.push(Span::styled(expand_tabs(text.to_string()), text_style)),
+    }
+
+    Line::from(spans)
 }
 
 // Help
 fn render_help(app: &App, total_help_area: ratatui::layout::Rect, frame: &mut ratatui::Frame) {
+    if let Some(notice) = app.notice.as_ref() {
+        let paragraph =
+            Paragraph::new(notice.message.clone()).style(Style::default().fg(Color::Yellow));
+        frame.render_widget(paragraph, total_help_area);
+        return;
+    }
+
     let paragraph_text = match app.running_state {
-        crate::ui::RunningState::Listing => "[Ctrl+k] : move up | [Ctrl+j] : move down | [Ctrl+d] : delete | [Esc] : exit | [Enter] : select",
+        crate::ui::RunningState::Listing => "[Ctrl+k] : move up | [Ctrl+j] : move down | [Ctrl+d] : delete | [Ctrl+p] : next profile | [Ctrl+f] : full text | [Ctrl+t] : tree | [tag:name] : filter by tag | [Esc] : exit | [Enter] : select",
         crate::ui::RunningState::Deleting => "[Y] : delete | [N / Esc] : cancel | [h/l] : Move selection | [Enter] : select",
         crate::ui::RunningState::Updating => "[Esc] : cancel",
         _ => "Press enter to exit the application", 
@@ -244,17 +560,17 @@ fn get_preview_line(entry: DirectoryPreviewEntry) -> Line<'static> {
         Style::default().fg(Color::White)
     };
 
-    return Line::from(vec![
+    Line::from(vec![
         Span::styled(tree_symbol, Style::default().fg(Color::DarkGray)),
         Span::styled(format!(" {} {}", icon, entry.name), style),
-    ]);
+    ])
 }
 
 fn get_path_line(path_string: String) -> Line<'static> {
-    return Line::from(vec![Span::styled(
-        format!("{}", path_string),
+    Line::from(vec![Span::styled(
+        path_string,
         Style::default().fg(Color::DarkGray),
-    )]);
+    )])
 }
 fn get_preview_areas(
     preview_area: ratatui::layout::Rect,
@@ -265,10 +581,10 @@ fn get_preview_areas(
 
     let [title_area, preview_area] = vertical_flex.areas(main_area);
 
-    return (
+    (
         title_area.offset(Offset { x: 0, y: 1 }),
         preview_area.inner(Margin::new(1, 1)),
-    );
+    )
 }
 
 struct LayoutAreas {
@@ -280,8 +596,42 @@ struct LayoutAreas {
     help_area: ratatui::layout::Rect,
 }
 
+fn get_tree_list_item<'a>(app: &App, row: &'a TreeRow, list_item_index: usize) -> ListItem<'a> {
+    let (path, depth, collapsed) = match row {
+        TreeRow::Bookmark { bookmark, depth } => {
+            return bookmark.to_list_item(app, list_item_index, *depth)
+        }
+        TreeRow::Folder {
+            path,
+            depth,
+            collapsed,
+        } => (path, *depth, *collapsed),
+    };
+
+    let is_selected = app.selection_index.unwrap_or(0) == list_item_index as i32;
+    let decoration_span = if is_selected {
+        Span::styled("> ", Style::default().fg(Color::Green))
+    } else {
+        Span::raw("  ")
+    };
+    let arrow = if collapsed { "▸ " } else { "▾ " };
+    let name = path.rsplit('/').next().unwrap_or_default().to_string();
+    let name_span = if is_selected {
+        Span::styled(name, FOLDER_STYLE.underlined())
+    } else {
+        Span::styled(name, FOLDER_STYLE)
+    };
+
+    ListItem::new(Line::from(vec![
+        decoration_span,
+        Span::raw(TREE_INDENT.repeat(depth)),
+        Span::styled(arrow, FOLDER_STYLE),
+        name_span,
+    ]))
+}
+
 impl Bookmark {
-    fn to_list_item(&self, app: &App, list_item_index: usize) -> ListItem<'_> {
+    fn to_list_item(&self, app: &App, list_item_index: usize, depth: usize) -> ListItem<'_> {
         let is_selected = app.selection_index.unwrap_or(0) == list_item_index as i32;
 
         let is_directory =
@@ -294,7 +644,13 @@ impl Bookmark {
         } else {
             Span::raw("  ")
         };
-        let icon_span = if is_directory {
+        let icon_span = if self.kind == BookmarkKind::Url {
+            Span::styled(" ", URL_STYLE)
+        } else if self.kind == BookmarkKind::Command {
+            Span::styled(" ", COMMAND_STYLE)
+        } else if self.kind == BookmarkKind::Remote {
+            Span::styled("󰒋 ", REMOTE_STYLE)
+        } else if is_directory {
             Span::styled(" ", Style::default().fg(Color::Blue))
         } else {
             Span::styled(" ", Style::default().fg(Color::White))
@@ -308,13 +664,24 @@ impl Bookmark {
         } else {
             Span::raw(bookmark_name.clone())
         };
-
-        let bookmark_description_space =
-            if self.name.clone().unwrap_or("".to_string()).len() < BOOKMARK_TITLE_WIDTH {
-                " ".repeat(BOOKMARK_TITLE_WIDTH - bookmark_name.len())
-            } else {
-                "".to_string()
-            };
+        let position = self
+            .position()
+            .map(|position| format!(":{}", position))
+            .unwrap_or_default();
+        let position_span = Span::styled(position.clone(), POSITION_STYLE);
+        let is_dirty = app
+            .git_statuses
+            .get(self.path.as_deref().unwrap_or_default())
+            .is_some_and(|status| status.is_dirty());
+        let dirty_marker = if is_dirty { GIT_DIRTY_MARKER } else { "" };
+        let dirty_span = Span::styled(dirty_marker, GIT_DIRTY_STYLE);
+
+        let title_length = bookmark_name.len() + position.len() + dirty_marker.chars().count();
+        let bookmark_description_space = if title_length < BOOKMARK_TITLE_WIDTH {
+            " ".repeat(BOOKMARK_TITLE_WIDTH - title_length)
+        } else {
+            "".to_string()
+        };
         let bookmark_description_string = format!(
             "{}{}",
             bookmark_description_space,
@@ -325,13 +692,23 @@ impl Bookmark {
             Style::default().fg(Color::DarkGray),
         );
 
-        let line_content = vec![
+        let mut line_content = vec![
             decoration_span,
+            Span::raw(TREE_INDENT.repeat(depth)),
             icon_span,
             bookmark_name_span,
+            position_span,
+            dirty_span,
             bookmark_path_span,
         ];
 
+        if !self.tags.is_empty() {
+            line_content.push(Span::styled(
+                format!(" {}", get_tags_string(&self.tags)),
+                TAG_STYLE,
+            ));
+        }
+
         let line = Line::from(line_content);
         ListItem::new(line)
     }
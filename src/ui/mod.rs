@@ -1,4 +1,7 @@
-use crate::sqlite::SqliteRepository;
+use crate::fuzzy_filter;
+use crate::paths::DatabaseLocation;
+use crate::service;
+use crate::store::BookmarkStore;
 use ratatui::{
     crossterm::{
         cursor,
@@ -11,22 +14,36 @@ use ratatui::{
 };
 use std::{
     cmp::{max, min},
+    collections::HashSet,
     io,
-    time::Duration,
+    time::{Duration, Instant},
 };
 use tui_textarea::TextArea;
 
 mod deleting_popup;
-mod fuzzy_filter;
+mod git_status;
 mod list;
+mod tree;
 mod updating_popup;
 
-use super::Bookmark;
+use git_status::GitStatuses;
+use tree::TreeRow;
+
+use super::{Bookmark, BookmarkKind, SortOrder};
+
+// Maximum number of full-text search hits blended into the list
+const FTS_RESULTS_LIMIT: usize = 200;
+
+// How long notices stay in place of the help line
+const NOTICE_DURATION: Duration = Duration::from_secs(5);
 
 #[derive(Debug)]
 pub struct App {
-    // The sqlite repository
-    sqlite_repository: SqliteRepository,
+    // Where the bookmarks are kept
+    store: Box<dyn BookmarkStore>,
+
+    // Where the bookmarks are stored, and the profile they belong to
+    location: DatabaseLocation,
 
     // The running state
     running_state: RunningState,
@@ -34,6 +51,9 @@ pub struct App {
     // The search query
     search_bar: Option<TextArea<'static>>,
 
+    // How the search query is matched against the bookmarks
+    search_mode: SearchMode,
+
     // The selected bookmark
     selected_bookmark: Option<Bookmark>,
 
@@ -43,11 +63,38 @@ pub struct App {
     // The list of filtered bookmarks
     filtered_bookmarks: Option<Vec<Bookmark>>,
 
+    // Whether the list is flat or grouped into folders
+    list_view: ListView,
+
+    // The full path of every folder
+    folders: Vec<String>,
+
+    // The folders whose content is hidden in the tree view
+    collapsed_folders: HashSet<String>,
+
+    // The lines of the tree view, in place of the filtered bookmarks
+    tree_rows: Option<Vec<TreeRow>>,
+
     // The index of the selected bookmark
     selection_index: Option<i32>,
 
     // Running-state specific data
     deleting_state: Option<DeletingState>,
+
+    // Transient message shown in place of the help line
+    notice: Option<Notice>,
+
+    // Git status of the bookmarked directories, loaded in the background
+    git_statuses: GitStatuses,
+}
+
+#[derive(Debug, Clone)]
+struct Notice {
+    message: String,
+    shown_at: Instant,
+
+    // Whether pressing u undoes the change the notice is about
+    undoable: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -59,6 +106,21 @@ struct DeletingState {
     selection: Option<bool>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SearchMode {
+    // Fuzzy matching on the bookmark names
+    Fuzzy,
+    // Fuzzy matching blended with full-text search over name, path and description
+    FullText,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ListView {
+    Flat,
+    // Bookmarks under their folders, which can be collapsed
+    Tree,
+}
+
 #[derive(Debug, PartialEq)]
 pub enum RunningState {
     Listing,
@@ -69,66 +131,85 @@ pub enum RunningState {
 }
 
 impl App {
-    pub fn new() -> App {
-        let result = SqliteRepository::new();
-        if let Err(e) = result {
-            println!("{}", e);
-            std::process::exit(1);
-        }
-
+    // The store is the one opened at the location
+    pub fn new(store: Box<dyn BookmarkStore>, location: DatabaseLocation) -> App {
         App {
-            sqlite_repository: result.unwrap(),
+            store,
+            location,
             running_state: RunningState::Listing,
             search_bar: Option::from(TextArea::new(vec![])),
+            search_mode: SearchMode::Fuzzy,
             selected_bookmark: None,
             bookmarks: None,
             filtered_bookmarks: None,
+            list_view: ListView::Flat,
+            folders: vec![],
+            collapsed_folders: HashSet::new(),
+            tree_rows: None,
             selection_index: Option::from(0),
             deleting_state: None,
+            notice: None,
+            git_statuses: GitStatuses::new(),
         }
     }
 
+    // Start with the search bar filled in, such as with a name that matched several bookmarks
+    pub fn set_query(&mut self, query: &str) {
+        let mut search_bar = TextArea::new(vec![query.to_string()]);
+        search_bar.move_cursor(tui_textarea::CursorMove::End);
+        self.search_bar = Option::from(search_bar);
+    }
+
     pub fn run(&mut self, print_command: bool) -> Result<(), AppError> {
         let mut terminal = self.init_terminal();
 
-        // Get the list of bookmarks from the sqlite repository
-        let bookmark_list_result = self.sqlite_repository.get_all_bookmarks();
-        if let Err(_) = bookmark_list_result {
+        // Get the list of bookmarks from the store
+        let bookmark_list_result = self.store.get_all_bookmarks();
+        if bookmark_list_result.is_err() {
             self.restore(terminal);
             return Err(AppError::InternalError(
                 "Error while getting bookmarks".to_string(),
             ));
         }
 
-        self.bookmarks = Option::from(bookmark_list_result.unwrap());
+        let mut bookmarks = bookmark_list_result.unwrap();
+        SortOrder::Frecency.sort(&mut bookmarks);
+        self.bookmarks = Option::from(bookmarks);
 
         // Initialize the filtered bookmarks
         self.filtered_bookmarks = self.bookmarks.clone();
 
         // Initialize the selected bookmark
         self.update_selected_bookmark();
+        self.request_git_statuses();
 
-        // Start the main loop
+        // Start the main loop. While idle it only wakes up to take in git statuses and
+        // expire notices, and redraws when one of them changed something
+        let mut needs_redraw = true;
         while self.running_state != RunningState::Done && self.running_state != RunningState::Exited
         {
-            let result = terminal.draw(|frame| self.render(frame));
-            if let Err(_) = result {
-                self.restore(terminal);
-                return Err(AppError::InternalError("Error while rendering".to_string()));
+            if needs_redraw {
+                let result = terminal.draw(|frame| self.render(frame));
+                if result.is_err() {
+                    self.restore(terminal);
+                    return Err(AppError::InternalError("Error while rendering".to_string()));
+                }
             }
 
-            let key_event_result = self.poll_event();
-            if let Err(e) = key_event_result {
+            let event_result = self.poll_event();
+            if let Err(e) = event_result {
                 self.restore(terminal);
                 return Err(e);
             }
-            self.handle_event(key_event_result.unwrap());
+            let event = event_result.unwrap();
+            // Other events, such as a resize, only need a redraw
+            needs_redraw = event.is_some();
 
-            // After handling the event, update the filtered bookmarks, the selection index and the selected bookmark
-            self.refresh_bookmarks();
-            self.update_filtered_bookmarks();
-            self.update_selection_index();
-            self.update_selected_bookmark();
+            if let Some(Event::Key(key_event)) = event {
+                self.handle_key_event(key_event);
+            }
+            needs_redraw |= self.expire_notice();
+            needs_redraw |= self.git_statuses.receive();
         }
         self.restore(terminal);
 
@@ -160,31 +241,36 @@ impl App {
     // Event handling
     //
 
-    fn poll_event(&self) -> Result<event::KeyEvent, AppError> {
-        if event::poll(Duration::from_millis(250)).is_ok() {
-            if let Event::Key(key) = event::read().unwrap() {
-                return Ok(key);
-            } else {
-                let key = event::KeyEvent {
-                    code: KeyCode::Null,
-                    modifiers: event::KeyModifiers::NONE,
-                    state: event::KeyEventState::empty(),
-                    kind: event::KeyEventKind::Release,
-                };
-                return Ok(key);
-            }
-        } else {
-            return Err(AppError::InternalError("Error while polling".to_string()));
+    // Returns nothing when nothing happened within the poll interval, so that anything
+    // loaded in the background in the meantime can be shown
+    fn poll_event(&self) -> Result<Option<Event>, AppError> {
+        match event::poll(Duration::from_millis(250)) {
+            Ok(true) => event::read()
+                .map(Some)
+                .map_err(|_| AppError::InternalError("Error while reading events".to_string())),
+            Ok(false) => Ok(None),
+            Err(_) => Err(AppError::InternalError("Error while polling".to_string())),
         }
     }
 
+    fn handle_key_event(&mut self, key_event: event::KeyEvent) {
+        self.handle_event(key_event);
+
+        // After handling the event, update the filtered bookmarks, the selection index and the selected bookmark
+        self.refresh_bookmarks();
+        self.update_filtered_bookmarks();
+        self.update_selection_index();
+        self.update_selected_bookmark();
+        self.request_git_statuses();
+    }
+
     fn handle_event(&mut self, key_event: event::KeyEvent) {
         // If the event is Ctrl+C, exit
-        if key_event.code == KeyCode::Char('c') {
-            if key_event.modifiers.contains(event::KeyModifiers::CONTROL) {
-                self.running_state = RunningState::Exited;
-                return;
-            }
+        if key_event.code == KeyCode::Char('c')
+            && key_event.modifiers.contains(event::KeyModifiers::CONTROL)
+        {
+            self.running_state = RunningState::Exited;
+            return;
         }
         match self.running_state {
             RunningState::Listing => {
@@ -208,6 +294,13 @@ impl App {
         }
 
         if key_event.code == KeyCode::Enter {
+            // In the tree view, Enter opens and closes the selected folder
+            if let Some(TreeRow::Folder { path, .. }) = self.get_selected_row() {
+                if !self.collapsed_folders.remove(&path) {
+                    self.collapsed_folders.insert(path);
+                }
+                return;
+            }
             self.running_state = RunningState::Done;
             return;
         }
@@ -222,6 +315,13 @@ impl App {
             return;
         }
 
+        // While a deletion notice is shown, u undoes the deletion instead of being typed
+        let can_undo = self.notice.as_ref().is_some_and(|notice| notice.undoable);
+        if can_undo && key_event.code == KeyCode::Char('u') && key_event.modifiers.is_empty() {
+            self.undo_last_change();
+            return;
+        }
+
         if key_event.modifiers.contains(event::KeyModifiers::CONTROL) {
             match key_event.code {
                 KeyCode::Char('d') => {
@@ -245,6 +345,23 @@ impl App {
                     // Decrement the selection index
                     self.decrement_selection_index();
                 }
+                KeyCode::Char('p') => {
+                    self.switch_to_next_profile();
+                }
+                KeyCode::Char('t') => {
+                    // Toggle the folder tree view
+                    self.list_view = match self.list_view {
+                        ListView::Flat => ListView::Tree,
+                        ListView::Tree => ListView::Flat,
+                    };
+                }
+                KeyCode::Char('f') => {
+                    // Toggle the full-text search
+                    self.search_mode = match self.search_mode {
+                        SearchMode::Fuzzy => SearchMode::FullText,
+                        SearchMode::FullText => SearchMode::Fuzzy,
+                    };
+                }
                 _ => {}
             }
             return;
@@ -294,33 +411,78 @@ impl App {
     }
 
     fn handle_updating_event(&mut self, key_event: event::KeyEvent) {
-        match key_event.code {
-            KeyCode::Esc => {
-                self.running_state = RunningState::Listing;
-            }
-            _ => {}
+        if key_event.code == KeyCode::Esc {
+            self.running_state = RunningState::Listing;
         }
     }
 
     fn update_filtered_bookmarks(&mut self) {
         let search_term = self.search_bar.as_ref().unwrap().lines()[0].clone();
         let search_term = search_term.trim().to_string();
-        let filtered_bookmarks = fuzzy_filter::get_filtered_bookmarks(
-            self.bookmarks.as_ref().unwrap().clone(),
-            search_term,
-        );
+        let is_searching = !search_term.is_empty();
+
+        let filtered_bookmarks = match self.search_mode {
+            SearchMode::Fuzzy => fuzzy_filter::get_filtered_bookmarks(
+                self.bookmarks.as_ref().unwrap().clone(),
+                search_term,
+            ),
+            SearchMode::FullText => {
+                // Fall back to fuzzy matching alone if the full-text search fails
+                let (_, fts_term) = fuzzy_filter::split_search_term(&search_term);
+                let fts_results = self
+                    .store
+                    .search_bookmarks(&fts_term, FTS_RESULTS_LIMIT)
+                    .unwrap_or_default();
+                fuzzy_filter::get_blended_bookmarks(
+                    self.bookmarks.as_ref().unwrap().clone(),
+                    fts_results,
+                    search_term,
+                )
+            }
+        };
+        self.tree_rows = match self.list_view {
+            ListView::Flat => None,
+            ListView::Tree => Option::from(tree::get_tree_rows(
+                &filtered_bookmarks,
+                &self.folders,
+                &self.collapsed_folders,
+                is_searching,
+            )),
+        };
         self.filtered_bookmarks = Option::from(filtered_bookmarks);
     }
 
+    // The number of lines in the list, bookmarks or tree rows
+    fn get_row_count(&self) -> i32 {
+        match &self.tree_rows {
+            Some(tree_rows) => tree_rows.len() as i32,
+            None => self.filtered_bookmarks.as_ref().unwrap().len() as i32,
+        }
+    }
+
+    fn get_selected_row(&self) -> Option<TreeRow> {
+        self.tree_rows
+            .as_ref()?
+            .get(self.selection_index.unwrap() as usize)
+            .cloned()
+    }
+
     fn update_selection_index(&mut self) {
         // If the selection index is out of bounds, set it to the last index
-        let bookmark_count = self.filtered_bookmarks.as_ref().unwrap().len() as i32;
+        let bookmark_count = self.get_row_count();
         if self.selection_index.unwrap() < 0 || self.selection_index.unwrap() > bookmark_count - 1 {
             self.selection_index = Option::from(bookmark_count - 1);
         }
     }
 
     fn update_selected_bookmark(&mut self) {
+        if self.tree_rows.is_some() {
+            self.selected_bookmark = self
+                .get_selected_row()
+                .and_then(|row| row.bookmark().cloned());
+            return;
+        }
+
         self.selected_bookmark = self
             .filtered_bookmarks
             .as_ref()
@@ -329,28 +491,143 @@ impl App {
             .cloned();
     }
 
+    // When the bookmarks cannot be read, such as after a broken hand edit of
+    // the TOML file, the error is shown and the last list read is kept
     fn refresh_bookmarks(&mut self) {
-        self.bookmarks = Option::from(self.sqlite_repository.get_all_bookmarks().unwrap());
+        let mut bookmarks = match self.store.get_all_bookmarks() {
+            Ok(bookmarks) => bookmarks,
+            Err(e) => {
+                self.show_notice(e.message(), false);
+                return;
+            }
+        };
+        SortOrder::Frecency.sort(&mut bookmarks);
+        self.bookmarks = Option::from(bookmarks);
         self.filtered_bookmarks = self.bookmarks.clone();
+
+        self.folders = self
+            .store
+            .get_folders()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+    }
+
+    // The selection first, then every directory in the list for the dirty markers
+    fn request_git_statuses(&mut self) {
+        let is_directory = |bookmark: &&Bookmark| bookmark.kind == BookmarkKind::Path;
+        if let Some(path) = self
+            .selected_bookmark
+            .as_ref()
+            .filter(is_directory)
+            .and_then(|bookmark| bookmark.path.as_deref())
+        {
+            self.git_statuses.request_selected(path);
+        }
+        for path in self
+            .bookmarks
+            .iter()
+            .flatten()
+            .filter(is_directory)
+            .filter_map(|bookmark| bookmark.path.as_deref())
+        {
+            self.git_statuses.request_listed(path);
+        }
     }
 
+    // Deleted bookmarks are moved to the trash, and can be brought back with u
     fn delete_selected_bookmark(&mut self) {
-        self.sqlite_repository
-            .delete_bookmark(
-                self.deleting_state
-                    .as_ref()
-                    .unwrap()
-                    .bookmark
-                    .as_ref()
-                    .unwrap()
-                    .id
-                    .unwrap(),
-            )
+        let bookmark = self
+            .deleting_state
+            .as_ref()
+            .unwrap()
+            .bookmark
+            .clone()
             .unwrap();
+        let name = bookmark.name.clone().unwrap_or_default();
+
+        match service::trash_bookmark(&*self.store, bookmark) {
+            Ok(_) => self.show_notice(format!("Deleted {} — press u to undo", name), true),
+            Err(e) => self.show_notice(e.message(), false),
+        }
+    }
+
+    fn undo_last_change(&mut self) {
+        match service::undo(&*self.store).as_deref() {
+            Ok([]) => self.show_notice("Nothing to undo".to_string(), false),
+            Ok([entry]) => self.show_notice(format!("Undid the {}", entry.describe()), false),
+            Ok(entries) => self.show_notice(format!("Undid {} changes", entries.len()), false),
+            Err(e) => self.show_notice(e.message(), false),
+        }
+    }
+
+    fn show_notice(&mut self, message: String, undoable: bool) {
+        self.notice = Option::from(Notice {
+            message,
+            shown_at: Instant::now(),
+            undoable,
+        });
+    }
+
+    // Returns whether the notice was removed
+    fn expire_notice(&mut self) -> bool {
+        let is_expired = self
+            .notice
+            .as_ref()
+            .is_some_and(|notice| notice.shown_at.elapsed() > NOTICE_DURATION);
+        if is_expired {
+            self.notice = None;
+        }
+        is_expired
+    }
+
+    // Open the database of the profile after the current one, for this session only
+    fn switch_to_next_profile(&mut self) {
+        // Profiles cannot be switched when the database was given explicitly
+        let Some(current_profile) = self.location.profile.clone() else {
+            return;
+        };
+        let Ok(data_dir) = crate::paths::data_dir() else {
+            return;
+        };
+
+        let profiles = crate::profile::list(&data_dir, self.location.backend);
+        let current_index = profiles
+            .iter()
+            .position(|profile| *profile == current_profile)
+            .unwrap_or(0);
+        let next_profile = profiles[(current_index + 1) % profiles.len()].clone();
+
+        let backend = self.location.backend;
+        let path = crate::profile::database_path(&data_dir, &next_profile, backend);
+        let Ok(store) = crate::store::open(backend, &path) else {
+            return;
+        };
+
+        self.store = store;
+        self.location = DatabaseLocation {
+            path,
+            backend,
+            profile: Some(next_profile),
+        };
+
+        // Start over in the new profile
+        self.search_bar = Option::from(TextArea::new(vec![]));
+        self.collapsed_folders.clear();
+        self.selection_index = Option::from(0);
+    }
+
+    // The title of the TUI, showing the profile in use
+    fn title(&self) -> String {
+        match &self.location.profile {
+            Some(profile) => format!("Oxmark - {}", profile),
+            None => "Oxmark".to_string(),
+        }
     }
 
     fn increment_selection_index(&mut self) {
-        let bookmark_count = self.filtered_bookmarks.as_ref().unwrap().len() as i32;
+        let bookmark_count = self.get_row_count();
         let selection_index = self.selection_index.unwrap();
         self.selection_index = Option::from(min(selection_index + 1, bookmark_count - 1));
     }
@@ -377,21 +654,15 @@ impl App {
             .unwrap();
 
         if self.running_state == RunningState::Done {
-            if print_command {
-                let is_directory = std::path::Path::new(&bookmark_path).is_dir();
-
-                let is_file = std::path::Path::new(&bookmark_path).is_file();
-
-                let editor = std::env::var("EDITOR").unwrap_or("vi".to_string());
-
-                let command = if is_directory {
-                    format!("cd {}", bookmark_path)
-                } else if is_file {
-                    format!("{} {}", editor, bookmark_path)
-                } else {
-                    "".to_string()
-                };
+            // Count the selection as a visit for the frecency ranking
+            let bookmark_id = self.selected_bookmark.as_ref().unwrap().id.unwrap();
+            if let Err(e) = self.store.record_visit(bookmark_id, crate::time::now()) {
+                eprintln!("{}", e);
+            }
 
+            if print_command {
+                let command = crate::shell::get_command(self.selected_bookmark.as_ref().unwrap())
+                    .unwrap_or_default();
                 println!("{}", command);
             } else {
                 println!("{}", bookmark_path);
@@ -464,3 +735,118 @@ impl std::fmt::Display for AppError {
         write!(f, "oxmark - AppError: {}", self.message())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Backend;
+    use crate::store::MemoryStore;
+    use event::{KeyEvent, KeyModifiers};
+
+    fn app_with(names: &[&str]) -> App {
+        let store = MemoryStore::new();
+        for name in names {
+            store
+                .create_bookmark(Bookmark {
+                    name: Some(name.to_string()),
+                    path: Some(format!("/nowhere/{}", name)),
+                    ..Bookmark::default()
+                })
+                .unwrap();
+        }
+        let location = DatabaseLocation {
+            path: "oxmark.toml".into(),
+            backend: Backend::Toml,
+            profile: None,
+        };
+
+        let mut app = App::new(Box::new(store), location);
+        app.refresh_bookmarks();
+        app.update_selected_bookmark();
+        app
+    }
+
+    fn press(app: &mut App, code: KeyCode) {
+        app.handle_key_event(KeyEvent::from(code));
+    }
+
+    fn type_text(app: &mut App, text: &str) {
+        text.chars().for_each(|c| press(app, KeyCode::Char(c)));
+    }
+
+    fn selected_name(app: &App) -> Option<String> {
+        app.selected_bookmark.as_ref()?.name.clone()
+    }
+
+    #[test]
+    fn selection_stays_within_the_list() {
+        let mut app = app_with(&["alpha", "beta", "gamma"]);
+        assert_eq!(app.selection_index, Some(0));
+
+        (0..5).for_each(|_| press(&mut app, KeyCode::Down));
+        assert_eq!(app.selection_index, Some(2));
+        let last = app.filtered_bookmarks.as_ref().unwrap()[2].clone();
+        assert_eq!(app.selected_bookmark, Some(last));
+
+        (0..5).for_each(|_| press(&mut app, KeyCode::Up));
+        assert_eq!(app.selection_index, Some(0));
+    }
+
+    #[test]
+    fn searching_narrows_the_selection() {
+        let mut app = app_with(&["alpha", "beta", "gamma"]);
+        (0..2).for_each(|_| press(&mut app, KeyCode::Down));
+
+        type_text(&mut app, "beta");
+
+        assert_eq!(app.filtered_bookmarks.as_ref().unwrap().len(), 1);
+        assert_eq!(app.selection_index, Some(0));
+        assert_eq!(selected_name(&app), Some("beta".to_string()));
+    }
+
+    #[test]
+    fn unreadable_bookmarks_keep_the_last_list() {
+        let dir = std::env::temp_dir().join(format!("oxmark-ui-broken-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let path = dir.join("oxmark.toml");
+        let store = MemoryStore::open_file(&path).unwrap();
+        store
+            .create_bookmark(Bookmark {
+                name: Some("alpha".to_string()),
+                path: Some("/nowhere/alpha".to_string()),
+                ..Bookmark::default()
+            })
+            .unwrap();
+        let location = DatabaseLocation {
+            path: path.clone(),
+            backend: Backend::Toml,
+            profile: None,
+        };
+        let mut app = App::new(Box::new(store), location);
+        app.refresh_bookmarks();
+        app.update_selected_bookmark();
+
+        // A hand edit leaving the file invalid
+        std::fs::write(&path, "[[bookmarks]\n").unwrap();
+        press(&mut app, KeyCode::Down);
+
+        assert_eq!(selected_name(&app), Some("alpha".to_string()));
+        let notice = app.notice.as_ref().unwrap();
+        assert!(notice.message.contains("Invalid"), "{}", notice.message);
+    }
+
+    #[test]
+    fn deleted_selection_can_be_undone() {
+        let mut app = app_with(&["alpha"]);
+
+        app.handle_key_event(KeyEvent::new(KeyCode::Char('d'), KeyModifiers::CONTROL));
+        assert_eq!(app.running_state, RunningState::Deleting);
+        press(&mut app, KeyCode::Char('y'));
+        assert_eq!(app.running_state, RunningState::Listing);
+        assert_eq!(selected_name(&app), None);
+
+        press(&mut app, KeyCode::Char('u'));
+        assert_eq!(selected_name(&app), Some("alpha".to_string()));
+        assert_eq!(app.search_bar.as_ref().unwrap().lines()[0], "");
+    }
+}
@@ -0,0 +1,121 @@
+use std::collections::HashSet;
+
+use super::Bookmark;
+
+// A line of the folder tree view
+#[derive(Debug, Clone)]
+pub enum TreeRow {
+    Folder {
+        path: String,
+        depth: usize,
+        collapsed: bool,
+    },
+    Bookmark {
+        bookmark: Box<Bookmark>,
+        depth: usize,
+    },
+}
+
+impl TreeRow {
+    pub fn bookmark(&self) -> Option<&Bookmark> {
+        match self {
+            TreeRow::Bookmark { bookmark, .. } => Some(bookmark),
+            TreeRow::Folder { .. } => None,
+        }
+    }
+}
+
+// Lay the bookmarks out under their folders, folders first and sorted by name.
+// Bookmarks keep their order inside a folder, so the best matches stay on top.
+// While searching, only the folders leading to a match are shown, all expanded
+pub fn get_tree_rows(
+    bookmarks: &[Bookmark],
+    folders: &[String],
+    collapsed_folders: &HashSet<String>,
+    is_searching: bool,
+) -> Vec<TreeRow> {
+    let mut shown_folders: HashSet<&str> = HashSet::new();
+    let visible_folders = bookmarks
+        .iter()
+        .filter_map(|bookmark| bookmark.folder.as_deref())
+        .chain(
+            folders
+                .iter()
+                .map(|folder| folder.as_str())
+                .filter(|_| !is_searching),
+        );
+    for folder in visible_folders {
+        // A folder is shown along with all of its parents
+        let mut end = folder.len();
+        loop {
+            shown_folders.insert(&folder[..end]);
+            match folder[..end].rfind('/') {
+                Some(index) => end = index,
+                None => break,
+            }
+        }
+    }
+
+    let mut rows = Vec::new();
+    add_rows(
+        &mut rows,
+        None,
+        0,
+        bookmarks,
+        &shown_folders,
+        collapsed_folders,
+        is_searching,
+    );
+    rows
+}
+
+fn add_rows(
+    rows: &mut Vec<TreeRow>,
+    parent: Option<&str>,
+    depth: usize,
+    bookmarks: &[Bookmark],
+    shown_folders: &HashSet<&str>,
+    collapsed_folders: &HashSet<String>,
+    is_searching: bool,
+) {
+    let mut children: Vec<&str> = shown_folders
+        .iter()
+        .filter(|folder| get_parent(folder) == parent)
+        .copied()
+        .collect();
+    children.sort();
+
+    for child in children {
+        let collapsed = !is_searching && collapsed_folders.contains(child);
+        rows.push(TreeRow::Folder {
+            path: child.to_string(),
+            depth,
+            collapsed,
+        });
+        if !collapsed {
+            add_rows(
+                rows,
+                Some(child),
+                depth + 1,
+                bookmarks,
+                shown_folders,
+                collapsed_folders,
+                is_searching,
+            );
+        }
+    }
+
+    bookmarks
+        .iter()
+        .filter(|bookmark| bookmark.folder.as_deref() == parent)
+        .for_each(|bookmark| {
+            rows.push(TreeRow::Bookmark {
+                bookmark: Box::new(bookmark.clone()),
+                depth,
+            })
+        });
+}
+
+fn get_parent(folder: &str) -> Option<&str> {
+    folder.rfind('/').map(|index| &folder[..index])
+}
@@ -0,0 +1,67 @@
+// Jumping to a remote bookmark runs ssh, replaced here by a stub recording its arguments
+
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn temporary_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("oxmark-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn oxmark(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_oxmark"))
+        .arg("--db")
+        .arg(dir.join("oxmark.db"))
+        .args(args)
+        .env("XDG_CONFIG_HOME", dir.join("config"))
+        .env_remove("OXMARK_BACKEND")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "oxmark {:?} failed", args);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn remote_bookmark_runs_ssh() {
+    let dir = temporary_dir("remote");
+
+    let stub_dir = dir.join("bin");
+    std::fs::create_dir_all(&stub_dir).unwrap();
+    let stub = stub_dir.join("ssh");
+    std::fs::write(
+        &stub,
+        format!(
+            "#!/bin/sh\nprintf '%s\\n' \"$@\" > '{}'\n",
+            dir.join("ssh-args").display()
+        ),
+    )
+    .unwrap();
+    std::fs::set_permissions(&stub, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    oxmark(&dir, &["add", "build01:/srv/my app", "app"]);
+    let command = oxmark(&dir, &["command", "app"]);
+
+    let path = format!(
+        "{}:{}",
+        stub_dir.display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(&command)
+        .env("PATH", path)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let args = std::fs::read_to_string(dir.join("ssh-args")).unwrap();
+    assert_eq!(
+        args.lines().collect::<Vec<&str>>(),
+        ["-t", "build01", "cd '/srv/my app' && exec $SHELL -l"]
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}